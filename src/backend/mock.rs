@@ -0,0 +1,804 @@
+/*!
+ * An in-memory audio backend, which follows a script of server events instead of connecting to a server.
+ * Requests made to it are recorded, and answered the way a server would, on the next update.
+ * Allows widget state transitions to be tested without a running pulse server.
+ */
+
+use std::cell::RefCell;
+use std::collections::{ HashSet, VecDeque };
+
+use libpulse::volume::ChannelVolumes;
+
+use crate::card::CardData;
+use crate::meter::MeterData;
+use crate::rules::AppProps;
+use super::{ Backend, Store, StreamType, StreamData, ModuleData, LoopbackData, ServerData, ConnectionState, DeviceState, MONITOR_SOURCE_SUFFIX };
+
+
+/**
+ * A server event, which the mock backend applies to its store when updated.
+ */
+
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum MockEvent {
+	Connection(ConnectionState),
+	Default(String, String),
+	Server(ServerData),
+	StreamUpdate(StreamType, MeterData, u32),
+	StreamRemove(StreamType, u32),
+	CardUpdate(CardData),
+	CardRemove(u32),
+	ModuleUpdate(ModuleData),
+	ModuleRemove(u32),
+	Loopback(u32, LoopbackData),
+	Error(String),
+	Peak(StreamType, u32, Vec<u32>)
+}
+
+
+/**
+ * A request made to the mock backend.
+ */
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockCall {
+	Connect,
+	Cleanup,
+	SetServer(Option<String>),
+	SetShowMonitors(bool),
+	SetShowHidden(bool),
+	SetDefaultSink(u32),
+	SetDefaultSource(u32),
+	SetVolume(StreamType, u32, ChannelVolumes),
+	SetMuted(StreamType, u32, bool),
+	SetCardProfile(u32, String),
+	MoveStream(StreamType, u32, u32),
+	SetPort(StreamType, u32, String),
+	SetSuspended(StreamType, u32, bool),
+	KillStream(StreamType, u32),
+	KillClient(u32),
+	LoadModule(String, String),
+	UnloadModule(u32),
+	StartLoopback(u32, u32, u32),
+	StopLoopback(u32),
+	PlayTestTone(u32, usize)
+}
+
+
+/**
+ * An audio backend that keeps its state in memory.
+ * Scripted events are applied one batch per update.
+ */
+
+pub struct MockBackend {
+	store: Store,
+
+	/** Batches of scripted events, one of which is applied per update. */
+	script: VecDeque<Vec<MockEvent>>,
+
+	/** Events produced in response to requests, applied on the next update. */
+	responses: RefCell<Vec<MockEvent>>,
+
+	/** Every request made to the backend, in order. */
+	pub calls: RefCell<Vec<MockCall>>,
+
+	/** The streams whose peaks were last requested. */
+	pub monitored: HashSet<(StreamType, u32)>,
+
+	/** Whether monitor sources are kept when they are updated. */
+	pub show_monitors: bool,
+
+	/** Whether streams matched by the hide list are kept when they are updated. */
+	pub show_hidden: bool,
+
+	/** The server that was last set, or None for the default server. */
+	pub server: Option<String>,
+}
+
+impl MockBackend {
+
+	/**
+	 * Creates a new mock backend with an empty store and script.
+	 */
+
+	pub fn new() -> Self {
+		MockBackend {
+			store: Store::new(),
+			script: VecDeque::new(),
+			responses: RefCell::new(Vec::new()),
+			calls: RefCell::new(Vec::new()),
+			monitored: HashSet::new(),
+			show_monitors: false,
+			show_hidden: false,
+			server: None
+		}
+	}
+
+
+	/**
+	 * Queues a batch of events, to be applied on a future update.
+	 * Batches are applied in the order they are scripted, one per update.
+	 *
+	 * * `events` - The events to apply together.
+	 */
+
+	pub fn script(&mut self, events: Vec<MockEvent>) {
+		self.script.push_back(events);
+	}
+
+
+	/**
+	 * Records a request.
+	 */
+
+	fn record(&self, call: MockCall) {
+		self.calls.borrow_mut().push(call);
+	}
+
+
+	/**
+	 * Queues an update for a stored stream, as the server would after a change.
+	 * Does nothing if the stream doesn't exist.
+	 *
+	 * * `t`      - The type of stream to update.
+	 * * `index`  - The index of the stream to update.
+	 * * `modify` - Applies the change to a copy of the stream's data.
+	 */
+
+	fn respond_stream(&self, t: StreamType, index: u32, modify: impl FnOnce(&mut StreamData)) {
+		if let Some(stream) = self.store.get_stream(t, index) {
+			let mut stream = stream.clone();
+			modify(&mut stream);
+			self.responses.borrow_mut().push(MockEvent::StreamUpdate(t, stream.data, stream.monitor_index));
+		}
+	}
+
+
+	/**
+	 * Checks if a stream should be hidden. Mock streams have no application properties,
+	 * so only the hide list's stream name filters apply to them.
+	 *
+	 * * `t`    - The type of the stream.
+	 * * `name` - The name of the stream.
+	 */
+
+	fn is_hidden(&self, t: StreamType, name: &str) -> bool {
+		(t == StreamType::Source && name.ends_with(MONITOR_SOURCE_SUFFIX) && !self.show_monitors)
+			|| (!self.show_hidden && self.store.hide_list.matches(t, name, &AppProps::default()))
+	}
+
+
+	/**
+	 * Removes the streams that should be hidden from the store. Hidden streams aren't kept,
+	 * they reappear the next time they're updated after being shown.
+	 */
+
+	fn remove_hidden(&mut self) {
+		for t in [ StreamType::Sink, StreamType::SinkInput, StreamType::Source, StreamType::SourceOutput ] {
+			let hidden: Vec<u32> = self.store.streams(t).iter()
+				.filter(|( _, stream )| self.is_hidden(t, &stream.data.name)).map(|( index, _ )| *index).collect();
+			for index in hidden { self.store.streams_mut(t).remove(&index); }
+		}
+
+		if !self.store.sinks.contains_key(&self.store.active_sink) { self.store.active_sink = self.store.default_sink; }
+		if !self.store.sources.contains_key(&self.store.active_source) { self.store.active_source = self.store.default_source; }
+	}
+
+
+	/**
+	 * Applies an event to the store.
+	 *
+	 * * `event` - The event to apply.
+	 */
+
+	fn apply(&mut self, event: MockEvent) {
+		match event {
+			MockEvent::Connection(state) => {
+				if state == ConnectionState::Lost { self.store.clear(); }
+				self.store.state = state;
+			},
+			MockEvent::Default(sink, source) => self.store.update_default(&sink, &source),
+			MockEvent::Server(data) => {
+				self.store.update_default(&data.default_sink, &data.default_source);
+				self.store.server = Some(data);
+			},
+			MockEvent::StreamUpdate(t, data, monitor_index) => {
+				if self.is_hidden(t, &data.name) {
+					self.store.streams_mut(t).remove(&data.index);
+					return;
+				}
+				let index = data.index;
+				match self.store.get_stream_mut(t, index) {
+					Some(stream) => {
+						stream.data = data;
+						stream.monitor_index = monitor_index;
+					},
+					None => {
+						let peak = vec![ 0; data.volume.len() as usize ];
+						self.store.streams_mut(t).insert(index, StreamData { data, peak, repetitions: 0, monitor_index, ..Default::default() });
+					}
+				}
+			},
+			MockEvent::StreamRemove(t, index) => { self.store.streams_mut(t).remove(&index); },
+			MockEvent::CardUpdate(data) => { self.store.cards.insert(data.index, data); },
+			MockEvent::CardRemove(index) => { self.store.cards.remove(&index); },
+			MockEvent::ModuleUpdate(data) => { self.store.modules.insert(data.index, data); },
+			MockEvent::ModuleRemove(index) => {
+				self.store.modules.remove(&index);
+				self.store.loopbacks.retain(|_, loopback| loopback.module != index);
+			},
+			MockEvent::Loopback(source, data) => { self.store.loopbacks.insert(source, data); },
+			MockEvent::Error(error) => self.store.errors.push(error),
+			MockEvent::Peak(t, index, peak) => {
+				if !self.monitored.contains(&(t, index)) { return; }
+				if let Some(stream) = self.store.get_stream_mut(t, index) { stream.peak = peak; }
+			}
+		}
+	}
+}
+
+impl Backend for MockBackend {
+	fn store(&self) -> &Store {
+		&self.store
+	}
+
+	fn store_mut(&mut self) -> &mut Store {
+		&mut self.store
+	}
+
+	fn connect(&mut self) {
+		self.record(MockCall::Connect);
+		self.store.state = ConnectionState::Connecting;
+	}
+
+	fn update(&mut self) -> bool {
+		let mut events = self.responses.replace(Vec::new());
+		if let Some(batch) = self.script.pop_front() { events.extend(batch); }
+
+		let received = !events.is_empty();
+		for event in events { self.apply(event); }
+		received
+	}
+
+	fn cleanup(&mut self) {
+		self.record(MockCall::Cleanup);
+		self.store.clear();
+	}
+
+	fn server(&self) -> Option<&str> {
+		self.server.as_deref()
+	}
+
+	fn set_server(&mut self, server: Option<String>) {
+		self.record(MockCall::SetServer(server.clone()));
+		self.server = server;
+		self.responses.borrow_mut().clear();
+		self.store.clear();
+		self.store.state = ConnectionState::Connecting;
+	}
+
+	fn set_monitored(&mut self, streams: HashSet<(StreamType, u32)>) {
+		for (t, index) in &self.monitored {
+			if streams.contains(&(*t, *index)) { continue; }
+			if let Some(stream) = self.store.get_stream_mut(*t, *index) {
+				for peak in &mut stream.peak { *peak = 0; }
+			}
+		}
+		self.monitored = streams;
+	}
+
+	fn set_show_monitors(&mut self, show: bool) {
+		self.record(MockCall::SetShowMonitors(show));
+		self.show_monitors = show;
+		if !show { self.remove_hidden(); }
+	}
+
+	fn set_show_hidden(&mut self, show: bool) {
+		self.record(MockCall::SetShowHidden(show));
+		self.show_hidden = show;
+		if !show { self.remove_hidden(); }
+	}
+
+	fn set_default_sink(&self, sink: u32) {
+		self.record(MockCall::SetDefaultSink(sink));
+		if let Some(sink) = self.store.sinks.get(&sink) {
+			let source = self.store.sources.get(&self.store.default_source).map(|s| s.data.name.clone()).unwrap_or_default();
+			self.responses.borrow_mut().push(MockEvent::Default(sink.data.name.clone(), source));
+		}
+	}
+
+	fn set_default_source(&self, source: u32) {
+		self.record(MockCall::SetDefaultSource(source));
+		if let Some(source) = self.store.sources.get(&source) {
+			let sink = self.store.sinks.get(&self.store.default_sink).map(|s| s.data.name.clone()).unwrap_or_default();
+			self.responses.borrow_mut().push(MockEvent::Default(sink, source.data.name.clone()));
+		}
+	}
+
+	fn set_volume(&self, t: StreamType, index: u32, volumes: ChannelVolumes) {
+		self.record(MockCall::SetVolume(t, index, volumes));
+		self.respond_stream(t, index, |stream| stream.data.volume = volumes);
+	}
+
+	fn set_muted(&self, t: StreamType, index: u32, mute: bool) {
+		self.record(MockCall::SetMuted(t, index, mute));
+		self.respond_stream(t, index, |stream| stream.data.muted = mute);
+	}
+
+	fn set_card_profile(&self, index: u32, profile: &str) {
+		self.record(MockCall::SetCardProfile(index, profile.to_owned()));
+		if let Some(card) = self.store.cards.get(&index) {
+			let mut card = card.clone();
+			card.active_profile = profile.to_owned();
+			self.responses.borrow_mut().push(MockEvent::CardUpdate(card));
+		}
+	}
+
+	fn move_stream(&self, t: StreamType, index: u32, device: u32) {
+		self.record(MockCall::MoveStream(t, index, device));
+		if t == StreamType::SinkInput || t == StreamType::SourceOutput {
+			self.respond_stream(t, index, |stream| stream.monitor_index = device);
+		}
+	}
+
+	fn set_port(&self, t: StreamType, index: u32, port: &str) {
+		self.record(MockCall::SetPort(t, index, port.to_owned()));
+		if t == StreamType::Sink || t == StreamType::Source {
+			self.respond_stream(t, index, |stream| stream.data.active_port = port.to_owned());
+		}
+	}
+
+	fn set_suspended(&self, t: StreamType, index: u32, suspend: bool) {
+		self.record(MockCall::SetSuspended(t, index, suspend));
+		if t == StreamType::Sink || t == StreamType::Source {
+			let state = if suspend { DeviceState::Suspended } else { DeviceState::Idle };
+			self.respond_stream(t, index, |stream| stream.data.state = Some(state));
+		}
+	}
+
+	fn kill_stream(&self, t: StreamType, index: u32) {
+		self.record(MockCall::KillStream(t, index));
+		if (t == StreamType::SinkInput || t == StreamType::SourceOutput) && self.store.get_stream(t, index).is_some() {
+			self.responses.borrow_mut().push(MockEvent::StreamRemove(t, index));
+		}
+	}
+
+	fn kill_client(&self, client: u32) {
+		self.record(MockCall::KillClient(client));
+		for t in [ StreamType::SinkInput, StreamType::SourceOutput ] {
+			for (index, stream) in self.store.streams(t) {
+				if stream.client == Some(client) { self.responses.borrow_mut().push(MockEvent::StreamRemove(t, *index)); }
+			}
+		}
+	}
+
+	fn load_module(&self, name: &str, argument: &str) {
+		self.record(MockCall::LoadModule(name.to_owned(), argument.to_owned()));
+
+		// Modules without a name can't exist, so they stand in for modules that fail to load.
+		if name.is_empty() {
+			self.responses.borrow_mut().push(MockEvent::Error("Failed to load the module.".to_owned()));
+			return;
+		}
+
+		let index = self.store.modules.keys().max().map_or(0, |i| i + 1);
+		self.responses.borrow_mut().push(MockEvent::ModuleUpdate(ModuleData {
+			index, name: name.to_owned(), argument: argument.to_owned(), n_used: None
+		}));
+	}
+
+	fn unload_module(&self, index: u32) {
+		self.record(MockCall::UnloadModule(index));
+		let event = if self.store.modules.contains_key(&index) { MockEvent::ModuleRemove(index) }
+			else { MockEvent::Error(format!("Failed to unload module #{}.", index)) };
+		self.responses.borrow_mut().push(event);
+	}
+
+	fn start_loopback(&self, source: u32, sink: u32, latency: u32) {
+		self.record(MockCall::StartLoopback(source, sink, latency));
+		if !self.store.sources.contains_key(&source) || !self.store.sinks.contains_key(&sink) { return; }
+
+		let mut responses = self.responses.borrow_mut();
+		if let Some(previous) = self.store.loopbacks.get(&source) { responses.push(MockEvent::ModuleRemove(previous.module)); }
+
+		let module = self.store.modules.keys().max().map_or(0, |i| i + 1);
+		responses.push(MockEvent::ModuleUpdate(ModuleData {
+			index: module, name: "module-loopback".to_owned(), argument: String::new(), n_used: None
+		}));
+		responses.push(MockEvent::Loopback(source, LoopbackData { module, sink, latency }));
+	}
+
+	fn stop_loopback(&self, source: u32) {
+		self.record(MockCall::StopLoopback(source));
+		if let Some(loopback) = self.store.loopbacks.get(&source) {
+			self.responses.borrow_mut().push(MockEvent::ModuleRemove(loopback.module));
+		}
+	}
+
+	fn play_test_tone(&mut self, sink: u32, channel: usize) {
+		self.record(MockCall::PlayTestTone(sink, channel));
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+
+	use libpulse::volume::{ Volume, ChannelVolumes };
+
+	use crate::meter::{ Meter, MeterData, ScaleOptions, MAX_NATURAL_VOL };
+	use crate::card::CardData;
+	use crate::hidden::{ Filter, FilterKey };
+	use super::{ MockBackend, MockEvent, MockCall };
+	use super::super::{ Backend, StreamType, ConnectionState, DeviceState, ServerData };
+
+
+	/**
+	 * Creates stereo volumes, with both channels at the volume specified.
+	 */
+
+	fn volumes(volume: u32) -> ChannelVolumes {
+		let mut volumes = ChannelVolumes::default();
+		volumes.set_len(2);
+		volumes.set(2, Volume(volume));
+		volumes
+	}
+
+
+	/**
+	 * Creates the data of a stream at the natural volume.
+	 */
+
+	fn stream(t: StreamType, index: u32, name: &str) -> MeterData {
+		MeterData { t, index, name: name.to_owned(), description: name.to_owned(), volume: volumes(MAX_NATURAL_VOL), ..MeterData::default() }
+	}
+
+
+	/**
+	 * Creates a mock backend that is connected to a server with a sink and a source, which are the defaults.
+	 */
+
+	fn connected() -> MockBackend {
+		let mut mock = MockBackend::new();
+		mock.connect();
+		mock.script(vec![
+			MockEvent::Connection(ConnectionState::Ready),
+			MockEvent::StreamUpdate(StreamType::Sink, stream(StreamType::Sink, 0, "speakers"), 0),
+			MockEvent::StreamUpdate(StreamType::Source, stream(StreamType::Source, 1, "microphone"), 1),
+			MockEvent::Default("speakers".to_owned(), "microphone".to_owned())
+		]);
+		assert!(mock.update());
+		mock
+	}
+
+
+	#[test]
+	fn connects_and_tracks_defaults() {
+		let mock = connected();
+		let store = mock.store();
+
+		assert_eq!(store.state, ConnectionState::Ready);
+		assert_eq!(store.default_sink, 0);
+		assert_eq!(store.active_sink, 0);
+		assert_eq!(store.default_source, 1);
+		assert_eq!(store.active_source, 1);
+		assert_eq!(*mock.calls.borrow(), vec![ MockCall::Connect ]);
+	}
+
+
+	#[test]
+	fn stream_added_changed_and_removed() {
+		let mut mock = connected();
+		mock.script(vec![ MockEvent::StreamUpdate(StreamType::SinkInput, stream(StreamType::SinkInput, 5, "music"), 0) ]);
+		assert!(mock.update());
+		assert_eq!(mock.store().get_stream(StreamType::SinkInput, 5).unwrap().peak, vec![ 0, 0 ]);
+
+		// A scale being dragged to half of its range, as a meter would request it.
+		let options = ScaleOptions::default();
+		let mut requested = volumes(MAX_NATURAL_VOL);
+		requested.scale(Meter::scale_volume(options, options.max_volume as f64 / 2.0));
+		mock.set_volume(StreamType::SinkInput, 5, requested);
+		assert_eq!(mock.store().get_stream(StreamType::SinkInput, 5).unwrap().data.volume, volumes(MAX_NATURAL_VOL));
+
+		assert!(mock.update());
+		let volume = mock.store().get_stream(StreamType::SinkInput, 5).unwrap().data.volume;
+		assert_eq!(volume, requested);
+		assert!((Meter::scale_value(options, volume.max()) - options.max_volume as f64 / 2.0).abs() < 1.0);
+
+		mock.set_muted(StreamType::SinkInput, 5, true);
+		assert!(mock.update());
+		assert!(mock.store().get_stream(StreamType::SinkInput, 5).unwrap().data.muted);
+
+		mock.script(vec![ MockEvent::StreamRemove(StreamType::SinkInput, 5) ]);
+		assert!(mock.update());
+		assert!(mock.store().get_stream(StreamType::SinkInput, 5).is_none());
+		assert!(!mock.update());
+
+		assert_eq!(*mock.calls.borrow(), vec![
+			MockCall::Connect,
+			MockCall::SetVolume(StreamType::SinkInput, 5, requested),
+			MockCall::SetMuted(StreamType::SinkInput, 5, true)
+		]);
+	}
+
+
+	#[test]
+	fn meter_peaks_follow_the_stream_volume() {
+		let mut mock = connected();
+		mock.script(vec![ MockEvent::StreamUpdate(StreamType::SinkInput, stream(StreamType::SinkInput, 5, "music"), 0) ]);
+		mock.update();
+
+		let mut requested = volumes(MAX_NATURAL_VOL);
+		requested.get_mut()[1] = Volume(MAX_NATURAL_VOL / 2);
+		mock.set_volume(StreamType::SinkInput, 5, requested);
+		mock.set_monitored(vec![ ( StreamType::SinkInput, 5 ) ].into_iter().collect());
+		mock.script(vec![ MockEvent::Peak(StreamType::SinkInput, 5, vec![ MAX_NATURAL_VOL, MAX_NATURAL_VOL / 2 ]) ]);
+		mock.update();
+
+		let options = ScaleOptions::default();
+		let natural = Meter::scale_value(options, Volume(MAX_NATURAL_VOL));
+		let half = Meter::scale_value(options, Volume(MAX_NATURAL_VOL / 2));
+		let music = mock.store().get_stream(StreamType::SinkInput, 5).unwrap();
+
+		let merged = Meter::peak_fills(&music.data, &music.peak, false, options);
+		assert_eq!(merged.len(), 1);
+		assert!((merged[0] - natural).abs() < 1e-6);
+
+		let split = Meter::peak_fills(&music.data, &music.peak, true, options);
+		assert_eq!(split.len(), 2);
+		assert!((split[0] - natural).abs() < 1e-6);
+		assert!((split[1] - half / 2.0).abs() < 1e-6);
+
+		mock.set_monitored(HashSet::new());
+		let music = mock.store().get_stream(StreamType::SinkInput, 5).unwrap();
+		assert!(Meter::peak_fills(&music.data, &music.peak, true, options).iter().all(|fill| *fill == 0.0));
+	}
+
+
+	#[test]
+	fn requests_for_missing_streams_are_ignored() {
+		let mut mock = connected();
+		mock.set_volume(StreamType::SinkInput, 9, volumes(0));
+		mock.set_muted(StreamType::SinkInput, 9, true);
+		assert!(!mock.update());
+		assert!(mock.store().sink_inputs.is_empty());
+	}
+
+
+	#[test]
+	fn streams_move_between_devices() {
+		let mut mock = connected();
+		mock.script(vec![
+			MockEvent::StreamUpdate(StreamType::Sink, stream(StreamType::Sink, 2, "headphones"), 2),
+			MockEvent::StreamUpdate(StreamType::SinkInput, stream(StreamType::SinkInput, 5, "music"), 0)
+		]);
+		mock.update();
+
+		mock.move_stream(StreamType::SinkInput, 5, 2);
+		mock.move_stream(StreamType::Sink, 0, 2);
+		mock.update();
+		assert_eq!(mock.store().get_stream(StreamType::SinkInput, 5).unwrap().monitor_index, 2);
+		assert_eq!(mock.store().get_stream(StreamType::Sink, 0).unwrap().monitor_index, 0);
+
+		mock.set_default_sink(2);
+		mock.update();
+		assert_eq!(mock.store().default_sink, 2);
+		assert_eq!(mock.store().active_sink, 2);
+	}
+
+
+	#[test]
+	fn peaks_are_only_kept_for_monitored_streams() {
+		let mut mock = connected();
+		mock.set_monitored(vec![ ( StreamType::Sink, 0 ) ].into_iter().collect());
+		mock.script(vec![
+			MockEvent::Peak(StreamType::Sink, 0, vec![ 100, 200 ]),
+			MockEvent::Peak(StreamType::Source, 1, vec![ 300, 400 ])
+		]);
+		mock.update();
+
+		assert_eq!(mock.store().get_stream(StreamType::Sink, 0).unwrap().peak, vec![ 100, 200 ]);
+		assert_eq!(mock.store().get_stream(StreamType::Source, 1).unwrap().peak, vec![ 0, 0 ]);
+
+		mock.set_monitored(HashSet::new());
+		assert_eq!(mock.store().get_stream(StreamType::Sink, 0).unwrap().peak, vec![ 0, 0 ]);
+	}
+
+
+	#[test]
+	fn modules_are_loaded_and_unloaded() {
+		let mut mock = connected();
+		mock.load_module("module-null-sink", "sink_name=test");
+		mock.load_module("", "");
+		mock.unload_module(42);
+		mock.update();
+
+		assert_eq!(mock.store().modules.get(&0).unwrap().name, "module-null-sink");
+		assert_eq!(mock.store().errors.len(), 2);
+
+		mock.unload_module(0);
+		mock.update();
+		assert!(mock.store().modules.is_empty());
+	}
+
+
+	#[test]
+	fn loopbacks_load_and_unload_their_module() {
+		let mut mock = connected();
+		mock.start_loopback(1, 0, 50);
+		mock.update();
+		let loopback = *mock.store().loopbacks.get(&1).unwrap();
+		assert_eq!(( loopback.sink, loopback.latency ), ( 0, 50 ));
+		assert!(mock.store().modules.contains_key(&loopback.module));
+
+		mock.stop_loopback(1);
+		mock.update();
+		assert!(mock.store().loopbacks.is_empty());
+		assert!(!mock.store().modules.contains_key(&loopback.module));
+	}
+
+
+	#[test]
+	fn killing_a_client_removes_its_streams() {
+		let mut mock = connected();
+		mock.script(vec![
+			MockEvent::StreamUpdate(StreamType::SinkInput, stream(StreamType::SinkInput, 5, "music"), 0),
+			MockEvent::StreamUpdate(StreamType::SourceOutput, stream(StreamType::SourceOutput, 8, "call"), 1),
+			MockEvent::StreamUpdate(StreamType::SinkInput, stream(StreamType::SinkInput, 9, "other"), 0)
+		]);
+		mock.update();
+		mock.store_mut().get_stream_mut(StreamType::SinkInput, 5).unwrap().client = Some(4);
+		mock.store_mut().get_stream_mut(StreamType::SourceOutput, 8).unwrap().client = Some(4);
+
+		mock.kill_stream(StreamType::SinkInput, 3);
+		mock.kill_client(4);
+		mock.update();
+		assert!(mock.store().source_outputs.is_empty());
+		assert_eq!(mock.store().sink_inputs.keys().copied().collect::<Vec<u32>>(), vec![ 9 ]);
+	}
+
+
+	#[test]
+	fn monitor_sources_are_hidden_unless_shown() {
+		let mut mock = connected();
+		let monitor = stream(StreamType::Source, 3, "speakers.monitor");
+		mock.script(vec![ MockEvent::StreamUpdate(StreamType::Source, monitor.clone(), 3) ]);
+		mock.update();
+		assert!(mock.store().get_stream(StreamType::Source, 3).is_none());
+
+		mock.set_show_monitors(true);
+		mock.script(vec![ MockEvent::StreamUpdate(StreamType::Source, monitor, 3) ]);
+		mock.update();
+		assert!(mock.store().get_stream(StreamType::Source, 3).is_some());
+
+		mock.set_active_source(3);
+		mock.set_show_monitors(false);
+		assert!(mock.store().get_stream(StreamType::Source, 3).is_none());
+		assert_eq!(mock.store().active_source, 1);
+	}
+
+
+	#[test]
+	fn hide_list_hides_matching_streams() {
+		let mut mock = connected();
+		mock.store_mut().hide_list.add(Filter::new(Some(StreamType::SinkInput), FilterKey::StreamName, "^Notification$").unwrap());
+
+		let notification = stream(StreamType::SinkInput, 6, "Notification");
+		mock.script(vec![
+			MockEvent::StreamUpdate(StreamType::SinkInput, notification.clone(), 0),
+			MockEvent::StreamUpdate(StreamType::SinkInput, stream(StreamType::SinkInput, 7, "Notifications Feed"), 0)
+		]);
+		mock.update();
+		assert!(mock.store().get_stream(StreamType::SinkInput, 6).is_none());
+		assert!(mock.store().get_stream(StreamType::SinkInput, 7).is_some());
+
+		mock.set_show_hidden(true);
+		mock.script(vec![ MockEvent::StreamUpdate(StreamType::SinkInput, notification, 0) ]);
+		mock.update();
+		assert!(mock.store().get_stream(StreamType::SinkInput, 6).is_some());
+
+		mock.set_show_hidden(false);
+		assert!(mock.store().get_stream(StreamType::SinkInput, 6).is_none());
+	}
+
+
+	#[test]
+	fn devices_change_ports() {
+		let mut mock = connected();
+		mock.set_port(StreamType::Sink, 0, "analog-output-headphones");
+		mock.set_port(StreamType::Source, 7, "analog-input-mic");
+		mock.update();
+
+		assert_eq!(mock.store().get_stream(StreamType::Sink, 0).unwrap().data.active_port, "analog-output-headphones");
+		assert_eq!(mock.store().get_stream(StreamType::Source, 1).unwrap().data.active_port, "");
+	}
+
+
+	#[test]
+	fn devices_suspend_and_resume() {
+		let mut mock = connected();
+		mock.set_suspended(StreamType::Source, 1, true);
+		mock.update();
+		assert_eq!(mock.store().get_stream(StreamType::Source, 1).unwrap().data.state, Some(DeviceState::Suspended));
+
+		mock.set_suspended(StreamType::Source, 1, false);
+		mock.update();
+		assert_eq!(mock.store().get_stream(StreamType::Source, 1).unwrap().data.state, Some(DeviceState::Idle));
+	}
+
+
+	#[test]
+	fn cards_change_profiles_and_are_removed() {
+		let mut mock = connected();
+		mock.script(vec![ MockEvent::CardUpdate(CardData {
+			index: 3, name: "Built-in Audio".to_owned(), icon: String::new(),
+			profiles: vec![ ( "off".to_owned(), "Off".to_owned() ), ( "stereo".to_owned(), "Stereo".to_owned() ) ],
+			active_profile: "off".to_owned()
+		}) ]);
+		mock.update();
+
+		mock.set_card_profile(3, "stereo");
+		mock.set_card_profile(4, "stereo");
+		mock.update();
+		assert_eq!(mock.store().cards.get(&3).unwrap().active_profile, "stereo");
+		assert_eq!(mock.store().cards.len(), 1);
+
+		mock.script(vec![ MockEvent::CardRemove(3) ]);
+		mock.update();
+		assert!(mock.store().cards.is_empty());
+	}
+
+
+	#[test]
+	fn server_information_sets_the_defaults() {
+		let mut mock = connected();
+		mock.script(vec![
+			MockEvent::StreamUpdate(StreamType::Sink, stream(StreamType::Sink, 2, "headphones"), 2),
+			MockEvent::Server(ServerData {
+				server_name: "pulseaudio".to_owned(),
+				default_sink: "headphones".to_owned(),
+				default_source: "microphone".to_owned(),
+				..ServerData::default()
+			})
+		]);
+		mock.update();
+
+		assert_eq!(mock.store().default_sink, 2);
+		assert_eq!(mock.store().server.as_ref().unwrap().server_name, "pulseaudio");
+	}
+
+
+	#[test]
+	fn losing_the_connection_clears_the_store() {
+		let mut mock = connected();
+		mock.script(vec![ MockEvent::Connection(ConnectionState::Lost) ]);
+		mock.update();
+
+		assert_eq!(mock.store().state, ConnectionState::Lost);
+		assert!(mock.store().sinks.is_empty());
+		assert_eq!(mock.store().active_sink, u32::MAX);
+	}
+
+
+	#[test]
+	fn changing_server_drops_pending_responses() {
+		let mut mock = connected();
+		mock.set_volume(StreamType::Sink, 0, volumes(0));
+		mock.set_server(Some("tcp:remote".to_owned()));
+
+		assert_eq!(mock.server(), Some("tcp:remote"));
+		assert_eq!(mock.store().state, ConnectionState::Connecting);
+		assert!(!mock.update());
+		assert!(mock.store().sinks.is_empty());
+
+		mock.play_test_tone(0, 1);
+		mock.cleanup();
+		assert_eq!(mock.calls.borrow()[2..], [
+			MockCall::SetServer(Some("tcp:remote".to_owned())),
+			MockCall::PlayTestTone(0, 1),
+			MockCall::Cleanup
+		]);
+	}
+}
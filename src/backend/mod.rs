@@ -0,0 +1,655 @@
+/*!
+ * Declares the audio backend trait, which the widgets use to read and control the audio server,
+ * as well as the data store that backends keep up to date. The libpulse implementation lives in
+ * the pulse module, an in-memory implementation is available for tests.
+ */
+
+use std::collections::{ HashMap, HashSet };
+
+use libpulse::volume::ChannelVolumes;
+
+use crate::card::CardData;
+use crate::meter::MeterData;
+use crate::rules::{ Rules, AppProps };
+use crate::hidden::HideList;
+use crate::events::EventLog;
+use crate::ducking::Ducking;
+
+#[cfg(test)]
+pub mod mock;
+
+/** The prefix of the names of the virtual devices created by Myxer, which distinguishes them from other devices. */
+pub const VIRTUAL_DEVICE_PREFIX: &str = "myxer.";
+
+/** The suffix of the names of monitor sources, which are hidden unless they are requested. */
+pub const MONITOR_SOURCE_SUFFIX: &str = ".monitor";
+
+/** The application name of Myxer's connection, which its own streams inherit. */
+pub const APP_NAME: &str = "Myxer";
+
+/** The name of the streams Myxer records peaks with. */
+pub const PEAK_STREAM_NAME: &str = "Peak Detect";
+
+/** The name of the stream Myxer plays the speaker test tone with. */
+pub const TEST_TONE_STREAM_NAME: &str = "Speaker Test";
+
+
+/**
+ * Checks if a stream was created by Myxer itself, to record peaks or play a test tone.
+ * These streams are never shown, monitored, or remembered, regardless of the hide list.
+ *
+ * * `name` - The name of the stream.
+ * * `app`  - The application properties of the stream.
+ */
+
+pub fn is_own_stream(name: &str, app: &AppProps) -> bool {
+	app.name == APP_NAME && (name == PEAK_STREAM_NAME || name == TEST_TONE_STREAM_NAME)
+}
+
+
+/**
+ * Represents a stream's underlying type.
+ */
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum StreamType {
+	#[default]
+	Sink,
+	SinkInput, Source, SourceOutput
+}
+
+
+/**
+ * Represents the state of the connection to the audio server.
+ */
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ConnectionState {
+	Connecting, Ready, Lost
+}
+
+
+/**
+ * Represents whether a sink or source is playing, open without playing, or suspended.
+ */
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeviceState {
+	Running, Idle, Suspended
+}
+
+impl DeviceState {
+
+	/**
+	 * Gets the state's name, for display.
+	 */
+
+	pub fn name(self) -> &'static str {
+		match self {
+			DeviceState::Running => "Running",
+			DeviceState::Idle => "Idle",
+			DeviceState::Suspended => "Suspended"
+		}
+	}
+}
+
+
+/**
+ * Stored representation of a stream.
+ * The stream index is not in this struct, but
+ * it is the index it is keyed under in its hashmap.
+ */
+
+#[derive(Debug, Clone, Default)]
+pub struct StreamData {
+	pub data: MeterData,
+
+	pub peak: Vec<u32>,
+	pub repetitions: u32,
+	pub monitor_index: u32,
+
+	/** The application properties of the stream, empty for devices. */
+	pub app: AppProps,
+
+	/** The index of the module that created the stream, if it was created by one. */
+	pub owner_module: Option<u32>,
+
+	/** The index of the client that owns the stream, None for devices. */
+	pub client: Option<u32>,
+
+	/** The index of the sink that the source monitors, if it is a monitor source. */
+	pub monitor_of: Option<u32>,
+}
+
+
+/**
+ * Holds a loaded server module's data.
+ */
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleData {
+	pub index: u32,
+
+	pub name: String,
+	pub argument: String,
+
+	/** How many times the module is in use, if the module reports it. */
+	pub n_used: Option<u32>,
+}
+
+
+/**
+ * Holds the audio server's information.
+ */
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServerData {
+	pub server_name: String,
+	pub server_version: String,
+	pub host_name: String,
+	pub user_name: String,
+
+	pub default_sink: String,
+	pub default_source: String,
+
+	/** The default sample spec and channel map, formatted the way the server formats them. */
+	pub sample_spec: String,
+	pub channel_map: String,
+
+	/** The cookie that identifies the server instance. */
+	pub cookie: u32,
+}
+
+impl ServerData {
+
+	/**
+	 * Gets the server information as labelled rows, in the order `pactl info` shows them.
+	 */
+
+	pub fn rows(&self) -> Vec<( &'static str, String )> {
+		vec![
+			( "Server Name", self.server_name.clone() ),
+			( "Server Version", self.server_version.clone() ),
+			( "Host Name", self.host_name.clone() ),
+			( "User Name", self.user_name.clone() ),
+			( "Default Sample Specification", self.sample_spec.clone() ),
+			( "Default Channel Map", self.channel_map.clone() ),
+			( "Default Sink", self.default_sink.clone() ),
+			( "Default Source", self.default_source.clone() ),
+			( "Cookie", format!("{:04x}:{:04x}", self.cookie >> 16, self.cookie & 0xffff) )
+		]
+	}
+}
+
+
+/**
+ * Holds the data of a loopback, which plays a source through a sink.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopbackData {
+	/** The index of the loopback module. */
+	pub module: u32,
+
+	/** The index of the sink the source is played through. */
+	pub sink: u32,
+
+	/** The latency of the loopback, in milliseconds. */
+	pub latency: u32,
+}
+
+
+/**
+ * Stores the known state of the audio server.
+ * Backends update it, and the widgets read from it.
+ */
+
+pub struct Store {
+	pub state: ConnectionState,
+
+	pub default_sink: u32,
+	pub default_source: u32,
+	pub active_sink: u32,
+	pub active_source: u32,
+
+	pub sinks: HashMap<u32, StreamData>,
+	pub sink_inputs: HashMap<u32, StreamData>,
+	pub sources: HashMap<u32, StreamData>,
+	pub source_outputs: HashMap<u32, StreamData>,
+	pub cards: HashMap<u32, CardData>,
+	pub modules: HashMap<u32, ModuleData>,
+
+	/** The loopbacks created by Myxer, keyed by the index of their source. */
+	pub loopbacks: HashMap<u32, LoopbackData>,
+
+	/** The server's information, once it has been received. */
+	pub server: Option<ServerData>,
+
+	/** Errors reported by the server in response to requests, oldest first. */
+	pub errors: Vec<String>,
+
+	/** The remembered application volumes. */
+	pub rules: Rules,
+
+	/** The filters that decide which streams are hidden. */
+	pub hide_list: HideList,
+
+	/** The settings for lowering the other playback streams during calls. */
+	pub ducking: Ducking,
+
+	/** The log of the server's activity, which is kept across reconnections. */
+	pub events: EventLog,
+}
+
+impl Store {
+
+	/**
+	 * Creates a new, empty store.
+	 */
+
+	pub fn new() -> Self {
+		Store {
+			state: ConnectionState::Connecting,
+
+			default_sink: u32::MAX,
+			default_source: u32::MAX,
+			active_sink: u32::MAX,
+			active_source: u32::MAX,
+
+			sinks: HashMap::new(),
+			sink_inputs: HashMap::new(),
+			sources: HashMap::new(),
+			source_outputs: HashMap::new(),
+			cards: HashMap::new(),
+			modules: HashMap::new(),
+			loopbacks: HashMap::new(),
+			server: None,
+			errors: Vec::new(),
+
+			rules: Rules::new(),
+			hide_list: HideList::new(),
+			ducking: Ducking::new(),
+			events: EventLog::new()
+		}
+	}
+
+
+	/**
+	 * Gets the stored streams of the type specified.
+	 *
+	 * * `t` - The type of streams to get.
+	 */
+
+	pub fn streams(&self, t: StreamType) -> &HashMap<u32, StreamData> {
+		match t {
+			StreamType::Sink => &self.sinks,
+			StreamType::SinkInput => &self.sink_inputs,
+			StreamType::Source => &self.sources,
+			StreamType::SourceOutput => &self.source_outputs,
+		}
+	}
+
+
+	/**
+	 * Gets a mutable reference to the stored streams of the type specified.
+	 *
+	 * * `t` - The type of streams to get.
+	 */
+
+	pub fn streams_mut(&mut self, t: StreamType) -> &mut HashMap<u32, StreamData> {
+		match t {
+			StreamType::Sink => &mut self.sinks,
+			StreamType::SinkInput => &mut self.sink_inputs,
+			StreamType::Source => &mut self.sources,
+			StreamType::SourceOutput => &mut self.source_outputs,
+		}
+	}
+
+
+	/**
+	 * Gets a stored stream.
+	 *
+	 * * `t`     - The type of stream to get.
+	 * * `index` - The index of the stream to get.
+	 */
+
+	pub fn get_stream(&self, t: StreamType, index: u32) -> Option<&StreamData> {
+		self.streams(t).get(&index)
+	}
+
+
+	/**
+	 * Gets a mutable reference to a stored stream.
+	 *
+	 * * `t`     - The type of stream to get.
+	 * * `index` - The index of the stream to get.
+	 */
+
+	pub fn get_stream_mut(&mut self, t: StreamType, index: u32) -> Option<&mut StreamData> {
+		self.streams_mut(t).get_mut(&index)
+	}
+
+
+	/**
+	 * Updates the stored default sink and source to the ones identified,
+	 * and makes them the active sink and source.
+	 *
+	 * * `sink`   - The name of the default sink.
+	 * * `source` - The name of the default source.
+	 */
+
+	pub fn update_default(&mut self, sink: &str, source: &str) {
+		for (i, v) in &self.sinks {
+			if v.data.name == sink {
+				self.default_sink = *i;
+				self.active_sink = *i;
+				break;
+			}
+		}
+
+		for (i, v) in &self.sources {
+			if v.data.name == source {
+				self.default_source = *i;
+				self.active_source = *i;
+				break;
+			}
+		}
+	}
+
+
+	/**
+	 * Removes all streams, cards, modules, and the server information, and resets the default and active devices.
+	 */
+
+	pub fn clear(&mut self) {
+		self.sinks.clear();
+		self.sink_inputs.clear();
+		self.sources.clear();
+		self.source_outputs.clear();
+		self.cards.clear();
+		self.modules.clear();
+		self.loopbacks.clear();
+		self.server = None;
+
+		self.default_sink = u32::MAX;
+		self.default_source = u32::MAX;
+		self.active_sink = u32::MAX;
+		self.active_source = u32::MAX;
+	}
+}
+
+
+/**
+ * The interface between the widgets and the audio server.
+ * Exposes the server's state through a Store, and methods to request changes.
+ * Changes are asynchronous, they are reflected in the store once the server reports them.
+ */
+
+pub trait Backend {
+
+	/**
+	 * Gets the store containing the server's state.
+	 */
+
+	fn store(&self) -> &Store;
+
+
+	/**
+	 * Gets a mutable reference to the store containing the server's state.
+	 */
+
+	fn store_mut(&mut self) -> &mut Store;
+
+
+	/**
+	 * Initiates a connection to the server.
+	 */
+
+	fn connect(&mut self);
+
+
+	/**
+	 * Handles queued updates from the server, updating the store.
+	 * Returns a boolean indicating that a layout refresh is required.
+	 */
+
+	fn update(&mut self) -> bool;
+
+
+	/**
+	 * Closes the connection to the server.
+	 * After this operation, no other methods should be called.
+	 */
+
+	fn cleanup(&mut self);
+
+
+	/**
+	 * Gets the server that is connected to, or None for the default server.
+	 */
+
+	fn server(&self) -> Option<&str>;
+
+
+	/**
+	 * Sets the server to connect to. If a connection has been initiated, it is torn down,
+	 * the store is cleared, and the new server is connected to.
+	 *
+	 * * `server` - A server string, like `unix:/path/to/socket` or `tcp:host:port`, or None for the default server.
+	 */
+
+	fn set_server(&mut self, server: Option<String>);
+
+
+	/**
+	 * Sets the streams whose peaks should be monitored.
+	 * Monitoring is started for the streams in the set, and stopped for every other stream,
+	 * whose peaks are reset. Streams in the set that don't exist yet are monitored once they appear.
+	 *
+	 * * `streams` - The type and index of each stream to monitor.
+	 */
+
+	fn set_monitored(&mut self, streams: HashSet<(StreamType, u32)>);
+
+
+	/**
+	 * Sets whether monitor sources are included in the store's sources.
+	 * When they are hidden, a monitor source that was active is replaced by the default source.
+	 *
+	 * * `show` - Whether monitor sources should be shown.
+	 */
+
+	fn set_show_monitors(&mut self, show: bool);
+
+
+	/**
+	 * Sets whether the streams matched by the store's hide list are included in the store.
+	 * The hide list is reapplied whenever its version changes.
+	 *
+	 * * `show` - Whether hidden streams should be shown.
+	 */
+
+	fn set_show_hidden(&mut self, show: bool);
+
+
+	/**
+	 * Sets the default sink to the index provided.
+	 * This is sometimes described as the fallback device.
+	 *
+	 * * `sink` - The sink index to set as the default.
+	 */
+
+	fn set_default_sink(&self, sink: u32);
+
+
+	/**
+	 * Sets the default source to the index provided.
+	 * This is sometimes described as the fallback device.
+	 *
+	 * * `source` - The source index to set as the default.
+	 */
+
+	fn set_default_source(&self, source: u32);
+
+
+	/**
+	 * Sets the volume of the stream to the volumes specified.
+	 *
+	 * * `t`       - The type of stream to set the volume of.
+	 * * `index`   - The index of the stream to set the volume of.
+	 * * `volumes` - The desired volumes to set the channels of the stream to.
+	 */
+
+	fn set_volume(&self, t: StreamType, index: u32, volumes: ChannelVolumes);
+
+
+	/**
+	 * Mutes or unmutes a stream.
+	 *
+	 * * `t`     - The type of stream to update.
+	 * * `index` - The index of the stream to update.
+	 * * `mute`  - Whether the stream should be muted or not.
+	 */
+
+	fn set_muted(&self, t: StreamType, index: u32, mute: bool);
+
+
+	/**
+	 * Set's a sound card's profile.
+	 *
+	 * * `index`   - The card index to update.
+	 * * `profile` - The profile name to update the card to.
+	 */
+
+	fn set_card_profile(&self, index: u32, profile: &str);
+
+
+	/**
+	 * Moves a stream to a different device.
+	 * Only sink inputs and source outputs can be moved, other stream types are ignored.
+	 *
+	 * * `t`      - The type of stream to move.
+	 * * `index`  - The index of the stream to move.
+	 * * `device` - The index of the sink or source to move the stream to.
+	 */
+
+	fn move_stream(&self, t: StreamType, index: u32, device: u32);
+
+
+	/**
+	 * Sets the active port of a sink or source.
+	 *
+	 * * `t`     - The type of device to update, other stream types are ignored.
+	 * * `index` - The index of the device to update.
+	 * * `port`  - The name of the port to activate.
+	 */
+
+	fn set_port(&self, t: StreamType, index: u32, port: &str);
+
+
+	/**
+	 * Suspends or resumes a device.
+	 * Suspended devices release their hardware until they are resumed.
+	 *
+	 * * `t`       - The type of device to update, other stream types are ignored.
+	 * * `index`   - The index of the device to update.
+	 * * `suspend` - Whether the device should be suspended or resumed.
+	 */
+
+	fn set_suspended(&self, t: StreamType, index: u32, suspend: bool);
+
+
+	/**
+	 * Disconnects a sink input or source output, which usually stops the application from playing or recording.
+	 *
+	 * * `t`     - The type of stream to disconnect, other stream types are ignored.
+	 * * `index` - The index of the stream to disconnect.
+	 */
+
+	fn kill_stream(&self, t: StreamType, index: u32);
+
+
+	/**
+	 * Disconnects a client from the server, along with all of its streams.
+	 *
+	 * * `client` - The index of the client to disconnect.
+	 */
+
+	fn kill_client(&self, client: u32);
+
+
+	/**
+	 * Loads a module into the server.
+	 * If the module can't be loaded, an error is added to the store.
+	 *
+	 * * `name`     - The name of the module to load, e.g. `module-null-sink`.
+	 * * `argument` - The module's arguments, which may be empty.
+	 */
+
+	fn load_module(&self, name: &str, argument: &str);
+
+
+	/**
+	 * Unloads a module from the server.
+	 * If the module can't be unloaded, an error is added to the store.
+	 *
+	 * * `index` - The index of the module to unload.
+	 */
+
+	fn unload_module(&self, index: u32);
+
+
+	/**
+	 * Plays a source through a sink, replacing the source's existing loopback if it has one.
+	 * The loopback is added to the store once it has been created.
+	 *
+	 * * `source`  - The index of the source to play.
+	 * * `sink`    - The index of the sink to play the source through.
+	 * * `latency` - The latency of the loopback, in milliseconds.
+	 */
+
+	fn start_loopback(&self, source: u32, sink: u32, latency: u32);
+
+
+	/**
+	 * Stops playing a source through a sink, if it is.
+	 *
+	 * * `source` - The index of the source.
+	 */
+
+	fn stop_loopback(&self, source: u32);
+
+
+	/**
+	 * Plays a short test tone on a single channel of a sink, stopping the previous test tone if one is playing.
+	 *
+	 * * `sink`    - The index of the sink to play the tone through.
+	 * * `channel` - The index of the channel in the sink's channel map.
+	 */
+
+	fn play_test_tone(&mut self, sink: u32, channel: usize);
+
+
+	/**
+	 * Sets the 'active' sink to the index provided.
+	 * This is the sink that is currently displayed on the interface.
+	 *
+	 * * `sink` - The sink index to set as active.
+	 */
+
+	fn set_active_sink(&mut self, sink: u32) {
+		self.store_mut().active_sink = sink;
+	}
+
+
+	/**
+	 * Sets the 'active' source to the index provided.
+	 * This is the source that is currently displayed on the interface.
+	 *
+	 * * `source` - The source to set as active.
+	 */
+
+	fn set_active_source(&mut self, source: u32) {
+		self.store_mut().active_source = source;
+	}
+}
@@ -7,7 +7,7 @@ use gtk::prelude::*;
 use glib::translate::ToGlib;
 use glib::translate::FromGlib;
 
-use crate::pulse::Pulse;
+use crate::backend::Backend;
 use crate::shared::Shared;
 
 
@@ -49,7 +49,7 @@ pub struct Card {
 	widgets: CardWidgets,
 
 	data: CardData,
-	pulse: Option<Shared<Pulse>>,
+	pulse: Option<Shared<dyn Backend>>,
 	combo_connect_id: Option<glib::signal::SignalHandlerId>,
 }
 
@@ -92,7 +92,7 @@ impl Card {
 	 * Creates a new card widget.
 	 */
 
-	pub fn new(pulse: Option<Shared<Pulse>>) -> Self {
+	pub fn new(pulse: Option<Shared<dyn Backend>>) -> Self {
 		let widgets = Card::build();
 		Self {
 			widget: widgets.root.clone(), widgets,
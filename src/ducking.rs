@@ -0,0 +1,158 @@
+/*!
+ * Configures ducking, which lowers the other playback streams while a communication stream is playing,
+ * such as a call. The settings are persisted to a file in the config directory.
+ */
+
+use crate::rules::AppProps;
+
+/** The media role of communication streams, which always trigger ducking. */
+pub const DUCKING_ROLE: &str = "phone";
+
+
+/**
+ * Stores the ducking settings, and persists them to a file in the config directory.
+ */
+
+pub struct Ducking {
+	pub enabled: bool,
+
+	/** How much the other streams are lowered by, as a percentage of their volume. */
+	pub amount: u32,
+
+	/** Application names or binaries whose streams trigger ducking, in addition to streams with the phone role. */
+	pub apps: Vec<String>,
+
+	path: Option<std::path::PathBuf>,
+}
+
+impl Ducking {
+
+	/**
+	 * Creates the default ducking settings, which aren't persisted. Ducking is disabled by default.
+	 */
+
+	pub fn new() -> Self {
+		Ducking { enabled: false, amount: 60, apps: Vec::new(), path: None }
+	}
+
+
+	/**
+	 * Loads the settings from the ducking file in the config directory.
+	 * Settings that are missing or invalid keep their default values.
+	 */
+
+	pub fn load() -> Self {
+		let mut ducking = Ducking::new();
+
+		if let Some(home_dir) = std::env::var_os("HOME") {
+			let mut path = std::path::PathBuf::new();
+			path.push(&home_dir);
+			path.push(".config");
+			path.push("myxer");
+			path.push("ducking.conf");
+
+			if let Ok(content) = std::fs::read_to_string(&path) {
+				for line in content.lines() {
+					let mut parts = line.splitn(2, '=');
+					let ( key, value ) = match ( parts.next(), parts.next() ) { ( Some(key), Some(value) ) => ( key, value ), _ => continue };
+
+					match key {
+						"enabled" => ducking.enabled = value.parse().unwrap_or(ducking.enabled),
+						"amount" => ducking.amount = value.parse().ok().filter(|amount| *amount <= 100).unwrap_or(ducking.amount),
+						"apps" => ducking.apps = Ducking::parse_apps(value),
+						_ => {}
+					}
+				}
+			}
+
+			ducking.path = Some(path);
+		}
+
+		ducking
+	}
+
+
+	/**
+	 * Writes the settings to the ducking file.
+	 */
+
+	fn save(&self) {
+		let path = match &self.path { Some(path) => path, None => return };
+		let content = format!("enabled={}\namount={}\napps={}\n", self.enabled, self.amount, self.apps.join(","));
+
+		if let Some(dir) = path.parent() { let _ = std::fs::create_dir_all(dir); }
+		if let Err(err) = std::fs::write(path, content) { eprintln!("Failed to save ducking settings: {}", err); }
+	}
+
+
+	/**
+	 * Splits a comma separated list of applications, ignoring empty entries.
+	 *
+	 * * `apps` - The list of applications.
+	 */
+
+	pub fn parse_apps(apps: &str) -> Vec<String> {
+		apps.split(',').map(|app| app.trim().to_lowercase()).filter(|app| !app.is_empty()).collect()
+	}
+
+
+	/**
+	 * Checks if a playback stream triggers ducking, either because it has the phone role,
+	 * or because its application name or binary contains one of the applications, ignoring case.
+	 *
+	 * * `app` - The application properties of the stream.
+	 */
+
+	pub fn is_trigger(&self, app: &AppProps) -> bool {
+		if app.role == DUCKING_ROLE { return true; }
+
+		let name = app.name.to_lowercase();
+		let binary = app.binary.to_lowercase();
+		self.apps.iter().any(|trigger| name.contains(trigger) || binary.contains(trigger))
+	}
+
+
+	/**
+	 * Gets the factor the volume of the other streams is multiplied by while ducking.
+	 */
+
+	pub fn level(&self) -> f64 {
+		1.0 - self.amount.min(100) as f64 / 100.0
+	}
+
+
+	/**
+	 * Enables or disables ducking.
+	 *
+	 * * `enabled` - Whether ducking is enabled.
+	 */
+
+	pub fn set_enabled(&mut self, enabled: bool) {
+		self.enabled = enabled;
+		self.save();
+	}
+
+
+	/**
+	 * Sets how much the other streams are lowered by.
+	 *
+	 * * `amount` - The amount, as a percentage of their volume.
+	 */
+
+	pub fn set_amount(&mut self, amount: u32) {
+		self.amount = amount.min(100);
+		self.save();
+	}
+
+
+	/**
+	 * Sets the applications whose streams trigger ducking.
+	 *
+	 * * `apps` - The application names or binaries.
+	 */
+
+	pub fn set_apps(&mut self, apps: Vec<String>) {
+		self.apps = apps;
+		self.save();
+	}
+}
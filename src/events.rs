@@ -0,0 +1,149 @@
+/*!
+ * Records the activity of the audio server, so that changes like streams moving between devices
+ * or cards changing profiles can be looked back on. The log is kept in memory, and can be exported.
+ */
+
+use std::collections::VecDeque;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+/** The number of events kept, older events are dropped once it is reached. */
+const MAX_EVENTS: usize = 2000;
+
+
+/**
+ * The category of an event, used for filtering.
+ */
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EventKind {
+	Connection,
+	Server,
+	Device,
+	Stream,
+	Card,
+	Module,
+	Error
+}
+
+impl EventKind {
+
+	/** All of the kinds, in the order they are displayed. */
+	pub const ALL: [EventKind; 7] = [ EventKind::Connection, EventKind::Server, EventKind::Device,
+		EventKind::Stream, EventKind::Card, EventKind::Module, EventKind::Error ];
+
+
+	/**
+	 * Gets the kind's label, for display.
+	 */
+
+	pub fn label(self) -> &'static str {
+		match self {
+			EventKind::Connection => "Connection",
+			EventKind::Server => "Server",
+			EventKind::Device => "Device",
+			EventKind::Stream => "Stream",
+			EventKind::Card => "Card",
+			EventKind::Module => "Module",
+			EventKind::Error => "Error"
+		}
+	}
+}
+
+
+/**
+ * An event that happened on the audio server.
+ */
+
+#[derive(Debug, Clone)]
+pub struct Event {
+	pub time: SystemTime,
+	pub kind: EventKind,
+
+	/** A human-readable description of what happened. */
+	pub description: String,
+}
+
+impl Event {
+
+	/**
+	 * Formats the event's time in the local timezone.
+	 */
+
+	pub fn timestamp(&self) -> String {
+		let secs = self.time.duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs() as i64);
+		glib::DateTime::from_unix_local(secs).format("%Y-%m-%d %H:%M:%S").map_or_else(String::new, |time| time.to_string())
+	}
+
+
+	/**
+	 * Formats the event as a line of text, as it is exported.
+	 */
+
+	pub fn line(&self) -> String {
+		format!("{}\t{}\t{}", self.timestamp(), self.kind.label(), self.description)
+	}
+}
+
+
+/**
+ * Stores the most recent events, oldest first.
+ */
+
+pub struct EventLog {
+	pub events: VecDeque<Event>,
+
+	/** Incremented every time an event is recorded or the log is cleared, so that views can tell when to refresh. */
+	pub version: u32,
+}
+
+impl EventLog {
+
+	/**
+	 * Creates an empty event log.
+	 */
+
+	pub fn new() -> Self {
+		EventLog { events: VecDeque::new(), version: 0 }
+	}
+
+
+	/**
+	 * Records an event that happened now, dropping the oldest event if the log is full.
+	 *
+	 * * `kind`        - The category of the event.
+	 * * `description` - A human-readable description of what happened.
+	 */
+
+	pub fn push(&mut self, kind: EventKind, description: String) {
+		if self.events.len() >= MAX_EVENTS { self.events.pop_front(); }
+		self.events.push_back(Event { time: SystemTime::now(), kind, description });
+		self.version += 1;
+	}
+
+
+	/**
+	 * Removes every event.
+	 */
+
+	pub fn clear(&mut self) {
+		self.events.clear();
+		self.version += 1;
+	}
+
+
+	/**
+	 * Writes the events to a text file, one per line.
+	 *
+	 * * `path` - The path of the file to write.
+	 */
+
+	pub fn export(&self, path: &std::path::Path) -> std::io::Result<()> {
+		let mut content = String::new();
+		for event in &self.events {
+			content.push_str(&event.line());
+			content.push('\n');
+		}
+
+		std::fs::write(path, content)
+	}
+}
@@ -0,0 +1,415 @@
+/*!
+ * Decides which streams are hidden from the mixer. Streams are matched against a list of filters,
+ * which can be edited by the user, and is persisted to a file in the config directory.
+ */
+
+use regex::Regex;
+
+use crate::backend::StreamType;
+use crate::rules::AppProps;
+
+
+/**
+ * The property of a stream that a filter is matched against.
+ */
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FilterKey {
+	/** The `application.process.binary` property. */
+	Binary,
+
+	/** The `application.name` property. */
+	AppName,
+
+	/** The `media.role` property. */
+	Role,
+
+	/** The name of the stream, which is matched as a regular expression. */
+	StreamName
+}
+
+impl FilterKey {
+
+	/** All of the keys, in the order they are displayed. */
+	pub const ALL: [FilterKey; 4] = [ FilterKey::Binary, FilterKey::AppName, FilterKey::Role, FilterKey::StreamName ];
+
+
+	/**
+	 * Gets the key's name, as used in the hidden streams file.
+	 */
+
+	pub fn name(self) -> &'static str {
+		match self {
+			FilterKey::Binary => "application.process.binary",
+			FilterKey::AppName => "application.name",
+			FilterKey::Role => "media.role",
+			FilterKey::StreamName => "name"
+		}
+	}
+
+
+	/**
+	 * Gets the key with the name specified, as returned by `name`.
+	 *
+	 * * `name` - The name of the key.
+	 */
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		FilterKey::ALL.iter().copied().find(|key| key.name() == name)
+	}
+
+
+	/**
+	 * Gets the key's label, for display.
+	 */
+
+	pub fn label(self) -> &'static str {
+		match self {
+			FilterKey::Binary => "Binary",
+			FilterKey::AppName => "Application",
+			FilterKey::Role => "Role",
+			FilterKey::StreamName => "Name (Regex)"
+		}
+	}
+}
+
+
+/**
+ * Gets the name of a stream type, as used in the hidden streams file, or "all" for filters that match every type.
+ *
+ * * `t` - The stream type, or None for every type.
+ */
+
+pub fn type_name(t: Option<StreamType>) -> &'static str {
+	match t {
+		None => "all",
+		Some(StreamType::Sink) => "sink",
+		Some(StreamType::SinkInput) => "output",
+		Some(StreamType::Source) => "source",
+		Some(StreamType::SourceOutput) => "input"
+	}
+}
+
+
+/**
+ * Gets the stream type with the name specified, as returned by `type_name`.
+ * Returns None if the name is invalid, and Some(None) for filters that match every type.
+ *
+ * * `name` - The name of the stream type.
+ */
+
+pub fn type_from_name(name: &str) -> Option<Option<StreamType>> {
+	match name {
+		"all" => Some(None),
+		"sink" => Some(Some(StreamType::Sink)),
+		"output" => Some(Some(StreamType::SinkInput)),
+		"source" => Some(Some(StreamType::Source)),
+		"input" => Some(Some(StreamType::SourceOutput)),
+		_ => None
+	}
+}
+
+
+/**
+ * A filter that hides the streams it matches.
+ */
+
+#[derive(Debug, Clone)]
+pub struct Filter {
+	/** The type of stream the filter applies to, or None to apply it to every type. */
+	pub t: Option<StreamType>,
+
+	pub key: FilterKey,
+
+	/** The value to match. Properties match if they contain it, ignoring case, except stream names, which it is a regex for. */
+	pub value: String,
+
+	regex: Option<Regex>,
+}
+
+impl Filter {
+
+	/**
+	 * Creates a filter, returning an error if the value is an invalid regular expression.
+	 *
+	 * * `t`     - The type of stream the filter applies to, or None for every type.
+	 * * `key`   - The property to match.
+	 * * `value` - The value to match.
+	 */
+
+	pub fn new(t: Option<StreamType>, key: FilterKey, value: &str) -> Result<Self, String> {
+		let regex = match key {
+			FilterKey::StreamName => Some(Regex::new(value).map_err(|err| err.to_string())?),
+			_ => None
+		};
+
+		Ok(Filter { t, key, value: if key == FilterKey::StreamName { value.to_owned() } else { value.to_lowercase() }, regex })
+	}
+
+
+	/**
+	 * Creates a filter that matches a stream's application as closely as its properties allow.
+	 * The binary is preferred, then the application name, then the exact name of the stream.
+	 *
+	 * * `t`    - The type of the stream.
+	 * * `name` - The name of the stream.
+	 * * `app`  - The application properties of the stream.
+	 */
+
+	pub fn for_stream(t: StreamType, name: &str, app: &AppProps) -> Self {
+		if !app.binary.is_empty() { return Filter::new(Some(t), FilterKey::Binary, &app.binary).unwrap(); }
+		if !app.name.is_empty() { return Filter::new(Some(t), FilterKey::AppName, &app.name).unwrap(); }
+		Filter::new(Some(t), FilterKey::StreamName, &format!("^{}$", regex::escape(name))).unwrap()
+	}
+
+
+	/**
+	 * Checks if the filter hides the stream specified.
+	 *
+	 * * `t`    - The type of the stream.
+	 * * `name` - The name of the stream.
+	 * * `app`  - The application properties of the stream, which are empty for devices.
+	 */
+
+	pub fn matches(&self, t: StreamType, name: &str, app: &AppProps) -> bool {
+		if self.t.map_or(false, |filter_t| filter_t != t) { return false; }
+
+		let property = match self.key {
+			FilterKey::Binary => &app.binary,
+			FilterKey::AppName => &app.name,
+			FilterKey::Role => &app.role,
+			FilterKey::StreamName => return self.regex.as_ref().map_or(false, |regex| regex.is_match(name))
+		};
+
+		!self.value.is_empty() && property.to_lowercase().contains(&self.value)
+	}
+}
+
+
+/**
+ * Stores the filters that hide streams, and persists them to a file in the config directory.
+ */
+
+pub struct HideList {
+	pub filters: Vec<Filter>,
+
+	/** Incremented every time the filters change, so that the backend and views can tell when to refresh. */
+	pub version: u32,
+
+	path: Option<std::path::PathBuf>,
+}
+
+impl HideList {
+
+	/**
+	 * Creates an empty hide list, which isn't persisted.
+	 */
+
+	pub fn new() -> Self {
+		HideList { filters: Vec::new(), version: 0, path: None }
+	}
+
+
+	/**
+	 * Loads the filters from the hidden streams file in the config directory.
+	 * If the file doesn't exist, the list contains the filter that hides pavucontrol's peak monitors,
+	 * which is saved to that file once the list is changed. Invalid lines are skipped.
+	 * Myxer's own streams are always hidden, so they don't need a filter.
+	 */
+
+	pub fn load() -> Self {
+		let mut list = HideList::new();
+		list.filters = vec![ Filter::new(Some(StreamType::SourceOutput), FilterKey::Binary, "pavucontrol").unwrap() ];
+
+		if let Some(home_dir) = std::env::var_os("HOME") {
+			let mut path = std::path::PathBuf::new();
+			path.push(&home_dir);
+			path.push(".config");
+			path.push("myxer");
+			path.push("hidden.conf");
+
+			if let Ok(content) = std::fs::read_to_string(&path) {
+				list.filters = content.lines().filter_map(HideList::parse_line).collect();
+			}
+
+			list.path = Some(path);
+		}
+
+		list
+	}
+
+
+	/**
+	 * Parses a line of the hidden streams file, returning None if it is invalid.
+	 * Lines contain the stream type, key, and value, separated by tabs.
+	 */
+
+	fn parse_line(line: &str) -> Option<Filter> {
+		let mut parts = line.splitn(3, '\t');
+		let t = type_from_name(parts.next()?)?;
+		let key = FilterKey::from_name(parts.next()?)?;
+		Filter::new(t, key, parts.next()?).ok()
+	}
+
+
+	/**
+	 * Writes the filters to the hidden streams file.
+	 */
+
+	fn save(&self) {
+		let path = match &self.path { Some(path) => path, None => return };
+
+		let mut content = String::new();
+		for filter in &self.filters {
+			content.push_str(&format!("{}\t{}\t{}\n", type_name(filter.t), filter.key.name(), filter.value));
+		}
+
+		if let Some(dir) = path.parent() { let _ = std::fs::create_dir_all(dir); }
+		if let Err(err) = std::fs::write(path, content) { eprintln!("Failed to save hidden streams: {}", err); }
+	}
+
+
+	/**
+	 * Checks if any filter hides the stream specified.
+	 *
+	 * * `t`    - The type of the stream.
+	 * * `name` - The name of the stream.
+	 * * `app`  - The application properties of the stream, which are empty for devices.
+	 */
+
+	pub fn matches(&self, t: StreamType, name: &str, app: &AppProps) -> bool {
+		self.filters.iter().any(|filter| filter.matches(t, name, app))
+	}
+
+
+	/**
+	 * Adds a filter, unless an identical one already exists.
+	 *
+	 * * `filter` - The filter to add.
+	 */
+
+	pub fn add(&mut self, filter: Filter) {
+		if self.filters.iter().any(|f| f.t == filter.t && f.key == filter.key && f.value == filter.value) { return; }
+		self.filters.push(filter);
+		self.version += 1;
+		self.save();
+	}
+
+
+	/**
+	 * Removes a filter.
+	 *
+	 * * `index` - The position of the filter to remove.
+	 */
+
+	pub fn remove(&mut self, index: usize) {
+		if index >= self.filters.len() { return; }
+		self.filters.remove(index);
+		self.version += 1;
+		self.save();
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use crate::backend::StreamType;
+	use crate::rules::AppProps;
+	use super::{ Filter, FilterKey, HideList, type_name, type_from_name };
+
+
+	/**
+	 * Creates the application properties of a stream.
+	 */
+
+	fn app(name: &str, binary: &str, role: &str) -> AppProps {
+		AppProps { name: name.to_owned(), binary: binary.to_owned(), role: role.to_owned(), ..AppProps::default() }
+	}
+
+
+	#[test]
+	fn property_filters_match_ignoring_case() {
+		let filter = Filter::new(None, FilterKey::Binary, "PavuControl").unwrap();
+		assert!(filter.matches(StreamType::SourceOutput, "Peak detect", &app("", "pavucontrol", "")));
+		assert!(filter.matches(StreamType::SinkInput, "", &app("", "/usr/bin/pavucontrol-qt", "")));
+		assert!(!filter.matches(StreamType::SinkInput, "pavucontrol", &app("pavucontrol", "mpv", "")));
+
+		let filter = Filter::new(Some(StreamType::SinkInput), FilterKey::Role, "event").unwrap();
+		assert!(filter.matches(StreamType::SinkInput, "", &app("", "", "event")));
+		assert!(!filter.matches(StreamType::SourceOutput, "", &app("", "", "event")));
+		assert!(!filter.matches(StreamType::SinkInput, "", &app("", "", "music")));
+
+		let filter = Filter::new(None, FilterKey::AppName, "").unwrap();
+		assert!(!filter.matches(StreamType::SinkInput, "", &app("Firefox", "firefox", "")));
+	}
+
+
+	#[test]
+	fn name_filters_match_as_regular_expressions() {
+		let filter = Filter::new(Some(StreamType::Source), FilterKey::StreamName, "^alsa_input\\..*usb").unwrap();
+		assert!(filter.matches(StreamType::Source, "alsa_input.usb-Blue_Yeti", &AppProps::default()));
+		assert!(!filter.matches(StreamType::Source, "alsa_input.pci-0000", &AppProps::default()));
+		assert!(!filter.matches(StreamType::Sink, "alsa_input.usb-Blue_Yeti", &AppProps::default()));
+
+		assert_eq!(Filter::new(None, FilterKey::StreamName, "Notification").unwrap().value, "Notification");
+		assert!(Filter::new(None, FilterKey::StreamName, "(unclosed").is_err());
+	}
+
+
+	#[test]
+	fn stream_filters_prefer_the_binary() {
+		let filter = Filter::for_stream(StreamType::SinkInput, "Playback", &app("Firefox", "firefox", ""));
+		assert_eq!(( filter.t, filter.key, filter.value.as_str() ), ( Some(StreamType::SinkInput), FilterKey::Binary, "firefox" ));
+
+		let filter = Filter::for_stream(StreamType::SinkInput, "Playback", &app("Firefox", "", ""));
+		assert_eq!(( filter.key, filter.value.as_str() ), ( FilterKey::AppName, "firefox" ));
+
+		let filter = Filter::for_stream(StreamType::SourceOutput, "Audio (2) [x]", &AppProps::default());
+		assert_eq!(filter.key, FilterKey::StreamName);
+		assert!(filter.matches(StreamType::SourceOutput, "Audio (2) [x]", &AppProps::default()));
+		assert!(!filter.matches(StreamType::SourceOutput, "Audio (2) [x] 2", &AppProps::default()));
+	}
+
+
+	#[test]
+	fn lines_are_parsed() {
+		let filter = HideList::parse_line("input\tapplication.process.binary\tpavucontrol").unwrap();
+		assert_eq!(( filter.t, filter.key, filter.value.as_str() ), ( Some(StreamType::SourceOutput), FilterKey::Binary, "pavucontrol" ));
+
+		let filter = HideList::parse_line("all\tname\t^a\tb$").unwrap();
+		assert_eq!(( filter.t, filter.key, filter.value.as_str() ), ( None, FilterKey::StreamName, "^a\tb$" ));
+
+		assert!(HideList::parse_line("").is_none());
+		assert!(HideList::parse_line("input\tapplication.process.binary").is_none());
+		assert!(HideList::parse_line("stream\tapplication.name\tmpv").is_none());
+		assert!(HideList::parse_line("input\tapplication.id\tmpv").is_none());
+		assert!(HideList::parse_line("all\tname\t[").is_none());
+	}
+
+
+	#[test]
+	fn names_round_trip() {
+		for t in [ None, Some(StreamType::Sink), Some(StreamType::SinkInput), Some(StreamType::Source), Some(StreamType::SourceOutput) ].iter() {
+			assert_eq!(type_from_name(type_name(*t)), Some(*t));
+		}
+		for key in FilterKey::ALL.iter() { assert_eq!(FilterKey::from_name(key.name()), Some(*key)); }
+	}
+
+
+	#[test]
+	fn duplicate_filters_are_not_added() {
+		let mut list = HideList::new();
+		list.add(Filter::new(None, FilterKey::AppName, "Zoom").unwrap());
+		list.add(Filter::new(None, FilterKey::AppName, "zoom").unwrap());
+		list.add(Filter::new(Some(StreamType::SinkInput), FilterKey::AppName, "zoom").unwrap());
+		assert_eq!(list.filters.len(), 2);
+		assert_eq!(list.version, 2);
+		assert!(list.matches(StreamType::SourceOutput, "", &app("ZOOM Meeting", "", "")));
+
+		list.remove(5);
+		list.remove(0);
+		assert_eq!(list.filters.len(), 1);
+		assert_eq!(list.version, 3);
+		assert!(!list.matches(StreamType::SourceOutput, "", &app("ZOOM Meeting", "", "")));
+	}
+}
@@ -5,6 +5,9 @@
 
 #![allow(clippy::tabs_in_doc_comments)]
 
+use std::rc::Rc;
+use std::cell::RefCell;
+
 use gio::prelude::*;
 
 mod card;
@@ -12,8 +15,14 @@ mod meter;
 mod pulse;
 mod window;
 mod shared;
+mod backend;
+mod rules;
+mod hidden;
+mod events;
+mod ducking;
 
 use pulse::Pulse;
+use backend::Backend;
 use window::Myxer;
 use shared::Shared;
 
@@ -28,27 +37,43 @@ use shared::Shared;
  */
 
 fn main() {
-	let pulse = Shared::new(Pulse::new());
+	let pulse: Shared<dyn Backend> = Shared::from_rc(Rc::new(RefCell::new(Pulse::new())));
 
 	let app = gtk::Application::new(Some("com.aurailus.myxer"), Default::default())
 		.expect("Failed to initialize GTK application.");
 
+	let server = server_arg();
 	let pulse_clone = pulse.clone();
 	app.connect_activate(|app| drop(app.register::<gio::Cancellable>(None)));
-	app.connect_startup(move |app| activate(app, &pulse_clone));
+	app.connect_startup(move |app| activate(app, &pulse_clone, server.clone()));
 	app.run(&[]);
 
 	pulse.borrow_mut().cleanup();
 }
 
 
+/**
+ * Gets the server passed on the command line, with `--server <server>`, `--server=<server>`, or `-s <server>`.
+ * The arguments aren't passed to GTK, so they are read from the environment.
+ */
+
+fn server_arg() -> Option<String> {
+	let mut args = std::env::args().skip(1);
+	while let Some(arg) = args.next() {
+		if arg == "--server" || arg == "-s" { return args.next(); }
+		if let Some(server) = arg.strip_prefix("--server=") { return Some(server.to_owned()); }
+	}
+	None
+}
+
+
 /**
  * Called by GTK when the application has initialized. Creates the main Myxer
  * instance, which controls the visible window, and handles the update loop.
  */
 
-fn activate(app: &gtk::Application, pulse: &Shared<Pulse>) {
-	let mut myxer = Myxer::new(app, pulse);
+fn activate(app: &gtk::Application, pulse: &Shared<dyn Backend>, server: Option<String>) {
+	let mut myxer = Myxer::new(app, pulse, server);
 
 	glib::timeout_add_local(1000 / 30, move || {
 		myxer.update();
@@ -3,20 +3,29 @@
  */
 
 use gtk::prelude::*;
+use gio::prelude::*;
 use libpulse::volume::{ Volume, ChannelVolumes };
+use libpulse::channelmap::{ Map as ChannelMap, Position as ChannelPosition };
 
 use crate::shared::Shared;
-use crate::pulse::{ Pulse, StreamType };
+use crate::backend::{ Backend, StreamType, DeviceState };
+use super::volume::{ VolumeCurve, format_percent, format_db };
 
 /** The maximum natural volume, i.e. 100% */
 pub const MAX_NATURAL_VOL: u32 = 65536;
 
-/** The maximum scale volume, i.e. 150% */
+/** The default maximum scale volume, i.e. 150% */
 pub const MAX_SCALE_VOL: u32 = (MAX_NATURAL_VOL as f64 * 1.5) as u32;
 
-/** The increment step of the scale, e.g. how far it moves when you press up & down. */
+/** The default increment step of the scale, e.g. how far it moves when you press up & down. */
 pub const SCALE_STEP: f64 = MAX_NATURAL_VOL as f64 / 20.0;
 
+/** The maximum scale volumes that can be chosen, as percentages. */
+pub const MAX_VOLUME_CHOICES: [u32; 4] = [ 100, 125, 150, 200 ];
+
+/** The scale steps that can be chosen, as percentages. */
+pub const STEP_CHOICES: [u32; 4] = [ 1, 2, 5, 10 ];
+
 /** The icon names for the input meter statuses. */
 pub const INPUT_ICONS: [&str; 4] = [ "microphone-sensitivity-muted-symbolic", "microphone-sensitivity-low-symbolic",
 	"microphone-sensitivity-medium-symbolic", "microphone-sensitivity-high-symbolic" ];
@@ -26,6 +35,20 @@ pub const OUTPUT_ICONS: [&str; 4] = [ "audio-volume-muted-symbolic", "audio-volu
 	"audio-volume-medium-symbolic", "audio-volume-high-symbolic" ];
 
 
+/**
+ * Holds a device port's data.
+ */
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PortData {
+	pub name: String,
+	pub description: String,
+
+	pub available: bool,
+	pub priority: u32,
+}
+
+
 /**
  * Holds a Meter widget's display data.
  */
@@ -40,7 +63,39 @@ pub struct MeterData {
 	pub description: String,
 
 	pub volume: ChannelVolumes,
+	pub channel_map: ChannelMap,
 	pub muted: bool,
+
+	pub ports: Vec<PortData>,
+	pub active_port: String,
+
+	/** The state of the device, None for streams. */
+	pub state: Option<DeviceState>,
+}
+
+
+/**
+ * Determines the range of a Meter's scales, and how they map to volumes.
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaleOptions {
+	pub curve: VolumeCurve,
+
+	/** The volume at the top of the scales. Volumes above it can't be set from the scales. */
+	pub max_volume: u32,
+
+	/** How far the scales move when you press up & down. */
+	pub step: f64,
+
+	/** Whether the maximum volume was chosen for the meter's device, instead of being the default. */
+	pub device_max: bool,
+}
+
+impl Default for ScaleOptions {
+	fn default() -> Self {
+		ScaleOptions { curve: VolumeCurve::default(), max_volume: MAX_SCALE_VOL, step: SCALE_STEP, device_max: false }
+	}
 }
 
 
@@ -52,8 +107,10 @@ pub struct MeterWidgets {
 	pub root: gtk::Box,
 	
 	pub icon: gtk::Image,
+	pub badge: gtk::Image,
 	pub label: gtk::Label,
 	pub select: gtk::Button,
+	pub select_label: gtk::Label,
 	pub app_button: gtk::Button,
 
 	pub status: gtk::Button,
@@ -61,6 +118,42 @@ pub struct MeterWidgets {
 
 	pub scales_outer: gtk::Box,
 	pub scales_inner: gtk::Box,
+
+	pub balance_outer: gtk::Box,
+	pub balance_inner: gtk::Box,
+}
+
+
+/**
+ * Gets a short label for a channel position, e.g. "FL" for front left.
+ *
+ * * `position` - The channel position to label.
+ */
+
+pub fn channel_label(position: ChannelPosition) -> String {
+	match position {
+		ChannelPosition::Mono => "M".to_owned(),
+		ChannelPosition::FrontLeft => "FL".to_owned(),
+		ChannelPosition::FrontRight => "FR".to_owned(),
+		ChannelPosition::FrontCenter => "FC".to_owned(),
+		ChannelPosition::RearCenter => "RC".to_owned(),
+		ChannelPosition::RearLeft => "RL".to_owned(),
+		ChannelPosition::RearRight => "RR".to_owned(),
+		ChannelPosition::Lfe => "LFE".to_owned(),
+		ChannelPosition::FrontLeftOfCenter => "FLC".to_owned(),
+		ChannelPosition::FrontRightOfCenter => "FRC".to_owned(),
+		ChannelPosition::SideLeft => "SL".to_owned(),
+		ChannelPosition::SideRight => "SR".to_owned(),
+		ChannelPosition::TopCenter => "TC".to_owned(),
+		ChannelPosition::TopFrontLeft => "TFL".to_owned(),
+		ChannelPosition::TopFrontRight => "TFR".to_owned(),
+		ChannelPosition::TopFrontCenter => "TFC".to_owned(),
+		ChannelPosition::TopRearLeft => "TRL".to_owned(),
+		ChannelPosition::TopRearRight => "TRR".to_owned(),
+		ChannelPosition::TopRearCenter => "TRC".to_owned(),
+		ChannelPosition::Invalid => "?".to_owned(),
+		aux => format!("A{}", aux as i32 - ChannelPosition::Aux0 as i32)
+	}
 }
 
 
@@ -87,6 +180,15 @@ pub trait Meter {
 	fn split_channels(&mut self, split: bool);
 
 
+	/**
+	 * Sets the range of the meter's scales, and how their positions map to volumes.
+	 *
+	 * * `options` - The options to use.
+	 */
+
+	fn set_scale_options(&mut self, options: ScaleOptions);
+
+
 	/**
 	 * Updates the meter's data, and visually refreshes the required widgets.
 	 */
@@ -95,31 +197,141 @@ pub trait Meter {
 
 
 	/**
-	 * Sets the meter's current peak.
+	 * Sets the meter's current peaks. When channels are split, each channel shows its own peak,
+	 * otherwise the loudest channel's peak is shown.
 	 *
-	 * * `peak` - The meter's peak, or None if no peak indicator should be shown.
+	 * * `peak` - The meter's peak for each channel, or None if no peak indicator should be shown.
 	 */
 
-	fn set_peak(&mut self, peak: Option<u32>);
+	fn set_peak(&mut self, peak: Option<Vec<u32>>);
 }
 
 impl dyn Meter {
 
+	/**
+	 * Converts a volume to the value of a scale.
+	 *
+	 * * `options` - The options of the scale.
+	 * * `volume`  - The volume to convert.
+	 */
+
+	pub fn scale_value(options: ScaleOptions, volume: Volume) -> f64 {
+		options.curve.to_position(volume, Volume(options.max_volume)) * options.max_volume as f64
+	}
+
+
+	/**
+	 * Converts the value of a scale to a volume.
+	 *
+	 * * `options` - The options of the scale.
+	 * * `value`   - The value of the scale.
+	 */
+
+	pub fn scale_volume(options: ScaleOptions, value: f64) -> Volume {
+		options.curve.to_volume(value / options.max_volume as f64, Volume(options.max_volume))
+	}
+
+
+	/**
+	 * Gets how far up a scale its peak visualizer should fill.
+	 * A full-scale peak fills the scale up to the current volume.
+	 *
+	 * * `options` - The options of the scale.
+	 * * `volume`  - The volume the scale is set to.
+	 * * `peak`    - The peak, where MAX_NATURAL_VOL is a full-scale signal.
+	 */
+
+	pub fn peak_fill(options: ScaleOptions, volume: Volume, peak: u32) -> f64 {
+		peak as f64 / MAX_NATURAL_VOL as f64 * Meter::scale_value(options, volume)
+	}
+
+
+	/**
+	 * Gets how far up each of a meter's scales their peak visualizers should fill.
+	 * When channels are split, each scale shows its own channel's peak, otherwise the loudest channel's peak is shown.
+	 *
+	 * * `data`    - The meter's data.
+	 * * `peaks`   - The meter's peak for each channel.
+	 * * `split`   - Whether or not the meter's channels are separated.
+	 * * `options` - The options of the meter's scales.
+	 */
+
+	pub fn peak_fills(data: &MeterData, peaks: &[u32], split: bool, options: ScaleOptions) -> Vec<f64> {
+		if split {
+			data.volume.get().iter().enumerate()
+				.map(|(i, volume)| Meter::peak_fill(options, *volume, peaks.get(i).copied().unwrap_or(0))).collect()
+		}
+		else { vec![ Meter::peak_fill(options, data.volume.max(), peaks.iter().copied().max().unwrap_or(0)) ] }
+	}
+
+
+	/**
+	 * Gets the label of a meter's status button, as a percentage or in decibels depending on the curve.
+	 * The volume is clamped to the top of the scales, so that it never reads above the meter's maximum.
+	 *
+	 * * `options` - The options of the meter's scales.
+	 * * `volume`  - The volume to display.
+	 */
+
+	pub fn status_label(options: ScaleOptions, volume: Volume) -> String {
+		let volume = Volume(volume.0.min(options.max_volume));
+		if options.curve == VolumeCurve::Decibel { format_db(volume) } else { format_percent(volume) }
+	}
+
+
+	/**
+	 * Describes the volume of each of a meter's channels, one per line, e.g. "FL: 85% (-4.2 dB)".
+	 *
+	 * * `data` - The meter data to describe.
+	 */
+
+	pub fn volume_tooltip(data: &MeterData) -> String {
+		let positions = data.channel_map.get();
+		data.volume.get().iter().enumerate().map(|(i, volume)| {
+			let label = positions.get(i).map(|p| channel_label(*p)).unwrap_or_else(|| (i + 1).to_string());
+			format!("{}: {} ({})", label, format_percent(*volume), format_db(*volume))
+		}).collect::<Vec<String>>().join("\n")
+	}
+
+
+	/**
+	 * Gets the tooltip for one of a meter's scales.
+	 *
+	 * * `data`    - The meter data the scale belongs to.
+	 * * `channel` - The channel the scale controls, or None if it controls all channels.
+	 */
+
+	pub fn scale_tooltip(data: &MeterData, channel: Option<usize>) -> String {
+		match channel {
+			Some(i) => {
+				let volume = data.volume.get().get(i).copied().unwrap_or(Volume::MUTED);
+				let name = data.channel_map.get().get(i).and_then(|p| ChannelPosition::to_pretty_string(*p)).unwrap_or_default();
+				format!("{}\n{} ({})", name, format_percent(volume), format_db(volume))
+			},
+			None => Meter::volume_tooltip(data)
+		}
+	}
+
+
 	/**
 	 * Builds a scale. This may be for a single channel, or all channels.
+	 *
+	 * * `options` - The options of the scale, which determine its range and where the natural volume mark is.
 	 */
 
-	fn build_scale() -> gtk::Scale {
-		let scale = gtk::Scale::with_range(gtk::Orientation::Vertical, 0.0, MAX_SCALE_VOL as f64, SCALE_STEP);
+	pub fn build_scale(options: ScaleOptions) -> gtk::Scale {
+		let scale = gtk::Scale::with_range(gtk::Orientation::Vertical, 0.0, options.max_volume as f64, options.step);
 
 		scale.set_inverted(true);
 		scale.set_draw_value(false);
-		scale.set_increments(SCALE_STEP, SCALE_STEP);
+		scale.set_increments(options.step, options.step);
 		scale.set_restrict_to_fill_level(false);
 
 		scale.add_mark(0.0, gtk::PositionType::Right, Some(""));
-		scale.add_mark(MAX_SCALE_VOL as f64, gtk::PositionType::Right, Some(""));
-		scale.add_mark(MAX_NATURAL_VOL as f64, gtk::PositionType::Right, Some(""));
+		scale.add_mark(options.max_volume as f64, gtk::PositionType::Right, Some(""));
+		if options.max_volume > MAX_NATURAL_VOL {
+			scale.add_mark(Meter::scale_value(options, Volume(MAX_NATURAL_VOL)), gtk::PositionType::Right, Some(""));
+		}
 
 		scale
 	}
@@ -129,12 +341,13 @@ impl dyn Meter {
 	 * Builds the required scales for a Meter.
 	 * This may be one or more, depending on the state of the `split` variable.
 	 *
-	 * * `pulse` - The pulse store to bind events to.
-	 * * `data`  - The meter data to base the scales off of.
-	 * * `split` - Whether or not one merged bar should be created, or individual bars for each channel.
+	 * * `pulse`   - The pulse store to bind events to.
+	 * * `data`    - The meter data to base the scales off of.
+	 * * `split`   - Whether or not one merged bar should be created, or individual bars for each channel.
+	 * * `options` - The options of the scales.
 	 */
 
-	pub fn build_scales(pulse: &Shared<Pulse>, data: &MeterData, split: bool) -> gtk::Box {
+	pub fn build_scales(pulse: &Shared<dyn Backend>, data: &MeterData, split: bool, options: ScaleOptions) -> gtk::Box {
 		let t = data.t;
 		let index = data.index;
 
@@ -142,10 +355,17 @@ impl dyn Meter {
 		let scales_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
 
 		if split {
-			for _ in 0 .. data.volume.len() {
-				let scale = Meter::build_scale();
+			for i in 0 .. data.volume.len() {
+				let scale = Meter::build_scale(options);
 				let pulse = pulse.clone();
 
+				if let Some(position) = data.channel_map.get().get(i as usize) {
+					let label = channel_label(*position);
+					scale.set_draw_value(true);
+					scale.set_value_pos(gtk::PositionType::Bottom);
+					scale.connect_format_value(move |_, _| label.clone());
+				}
+
 				scale.connect_change_value(move |scale, _, val| {
 					let parent = scale.get_parent().unwrap().downcast::<gtk::Box>().unwrap();
 					let children = parent.get_children();
@@ -157,8 +377,7 @@ impl dyn Meter {
 					for (i, w) in children.iter().enumerate().rev() {
 						let s = w.clone().downcast::<gtk::Scale>().unwrap();
 						let value = if *scale == s { val } else { s.get_value() };
-						let volume = Volume(value as u32);
-						volumes.set(i as u8 + 1, volume);
+						volumes.set(i as u8 + 1, Meter::scale_volume(options, value));
 					}
 
 					volumes.set_len(children.len() as u8);
@@ -173,14 +392,18 @@ impl dyn Meter {
 			}
 		}
 		else {
-			let scale = Meter::build_scale();
-			let channels = data.volume.len();
+			let scale = Meter::build_scale(options);
 			let pulse = pulse.clone();
 			scale.connect_change_value(move |_, _, value| {
-				let mut volumes = ChannelVolumes::default();
-				volumes.set_len(channels);
-				volumes.set(channels, Volume(value as u32));
 				let pulse = pulse.borrow_mut();
+
+				// Scale the channels proportionally, so that the balance between them is preserved.
+				let mut volumes = match pulse.store().get_stream(t, index) {
+					Some(stream) => stream.data.volume,
+					None => return gtk::Inhibit(false)
+				};
+				volumes.scale(Meter::scale_volume(options, value));
+
 				pulse.set_volume(t, index, volumes);
 				if volumes.max().0 > 0 { pulse.set_muted(t, index, false); }
 				gtk::Inhibit(false)
@@ -193,6 +416,225 @@ impl dyn Meter {
 	}
 
 
+	/**
+	 * Builds the balance and fade controls for a Meter.
+	 * These are only shown when channels are split, and only if the channel map supports them.
+	 *
+	 * * `pulse` - The pulse store to bind events to.
+	 * * `data`  - The meter data to base the controls off of.
+	 * * `split` - Whether or not the channels are split.
+	 */
+
+	pub fn build_balance(pulse: &Shared<dyn Backend>, data: &MeterData, split: bool) -> gtk::Box {
+		let t = data.t;
+		let index = data.index;
+		let map = data.channel_map;
+
+		let balance_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+		if !split { return balance_box; }
+
+		let controls = [ ( "balance", "Balance", map.can_balance() ), ( "fade", "Fade", map.can_fade() ) ];
+
+		for ( name, tooltip, enabled ) in &controls {
+			if !enabled { continue; }
+
+			let scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, -1.0, 1.0, 0.1);
+			scale.set_widget_name(name);
+			scale.set_tooltip_text(Some(tooltip));
+			scale.set_draw_value(false);
+			scale.set_increments(0.1, 0.1);
+			scale.add_mark(0.0, gtk::PositionType::Bottom, None);
+
+			let name = *name;
+			let pulse = pulse.clone();
+			scale.connect_change_value(move |_, _, value| {
+				let pulse = pulse.borrow_mut();
+
+				let mut volumes = match pulse.store().get_stream(t, index) {
+					Some(stream) => stream.data.volume,
+					None => return gtk::Inhibit(false)
+				};
+
+				let value = value.clamp(-1.0, 1.0) as f32;
+				if name == "balance" { volumes.set_balance(&map, value); }
+				else { volumes.set_fade(&map, value); }
+
+				pulse.set_volume(t, index, volumes);
+				gtk::Inhibit(false)
+			});
+
+			balance_box.pack_start(&scale, false, false, 0);
+		}
+
+		balance_box.show_all();
+		balance_box
+	}
+
+
+	/**
+	 * Updates the balance and fade controls built by `build_balance` to reflect the current volumes.
+	 *
+	 * * `balance_box` - The box containing the controls.
+	 * * `data`        - The meter data to read the volumes from.
+	 */
+
+	pub fn update_balance(balance_box: &gtk::Box, data: &MeterData) {
+		for child in &balance_box.get_children() {
+			let scale = child.clone().downcast::<gtk::Scale>().expect("Balance box has non-scale children.");
+			scale.set_value(match scale.get_widget_name().as_str() {
+				"balance" => data.volume.get_balance(&data.channel_map),
+				_ => data.volume.get_fade(&data.channel_map)
+			} as f64);
+		}
+	}
+
+
+	/**
+	 * Gets the tooltip for a device's app button, which shows its description and state.
+	 *
+	 * * `data` - The meter data of the device.
+	 */
+
+	pub fn device_tooltip(data: &MeterData) -> String {
+		match data.state {
+			Some(state) => format!("{}\n{}", data.description, state.name()),
+			None => data.description.clone()
+		}
+	}
+
+
+	/**
+	 * Adds an item to a device popup menu to suspend the device, or resume it if it is suspended.
+	 *
+	 * * `menu`  - The popup's menu box to add the item to.
+	 * * `pulse` - The pulse store to bind events to.
+	 * * `data`  - The meter data of the device.
+	 */
+
+	pub fn add_suspend_item(menu: &gtk::Box, pulse: &Shared<dyn Backend>, data: &MeterData) {
+		if data.state.is_none() { return; }
+		let suspended = data.state == Some(DeviceState::Suspended);
+
+		let button = gtk::ModelButton::new();
+		button.set_property_text(Some(if suspended { "Resume" } else { "Suspend" }));
+
+		let t = data.t;
+		let index = data.index;
+		let pulse = pulse.clone();
+		button.connect_clicked(move |_| pulse.borrow().set_suspended(t, index, !suspended));
+		menu.add(&button);
+	}
+
+
+	/**
+	 * Adds items to a device popup menu to change the device's active port.
+	 * Unavailable ports are shown, but cannot be selected.
+	 * Nothing is added if the device has less than two ports.
+	 *
+	 * * `menu`  - The popup's menu box to add the items to.
+	 * * `root`  - The popup itself, which is closed when a port is selected.
+	 * * `pulse` - The pulse store to bind events to.
+	 * * `data`  - The meter data of the device.
+	 */
+
+	pub fn add_port_items(menu: &gtk::Box, root: &gtk::PopoverMenu, pulse: &Shared<dyn Backend>, data: &MeterData) {
+		if data.ports.len() < 2 { return; }
+
+		menu.pack_start(&gtk::SeparatorMenuItem::new(), false, false, 3);
+
+		let label = gtk::Label::new(Some("Port"));
+		label.set_sensitive(false);
+		menu.pack_start(&label, true, true, 3);
+
+		let mut ports = data.ports.clone();
+		ports.sort_by_key(|p| std::cmp::Reverse(p.priority));
+
+		for port in ports {
+			let button = gtk::ModelButton::new();
+			button.set_property_role(gtk::ButtonRole::Radio);
+			button.set_property_active(port.name == data.active_port);
+			button.set_sensitive(port.available);
+			let button_label = gtk::Label::new(Some(&port.description));
+			button_label.set_ellipsize(pango::EllipsizeMode::End);
+			button_label.set_max_width_chars(18);
+			button.get_child().unwrap().downcast::<gtk::Box>().unwrap().add(&button_label);
+
+			let t = data.t;
+			let index = data.index;
+			let root = root.clone();
+			let pulse = pulse.clone();
+			button.connect_clicked(move |_| {
+				pulse.borrow_mut().set_port(t, index, &port.name);
+				root.popdown();
+			});
+
+			menu.add(&button);
+		}
+	}
+
+
+	/**
+	 * Adds items to a device popup menu to choose the device's maximum volume.
+	 * The choice is made by activating the `app.device_max_volume` action on the trigger,
+	 * with the percentage and the device name separated by a colon, or a percentage of 0 to use the default.
+	 *
+	 * * `menu`    - The popup's menu box to add the items to.
+	 * * `root`    - The popup itself, which is closed when an item is selected.
+	 * * `trigger` - The button that opened the popup.
+	 * * `data`    - The meter data of the device.
+	 * * `options` - The device's current scale options.
+	 */
+
+	pub fn add_max_volume_items(menu: &gtk::Box, root: &gtk::PopoverMenu, trigger: &gtk::Button, data: &MeterData, options: ScaleOptions) {
+		menu.pack_start(&gtk::SeparatorMenuItem::new(), false, false, 3);
+
+		let label = gtk::Label::new(Some("Maximum Volume"));
+		label.set_sensitive(false);
+		menu.pack_start(&label, true, true, 3);
+
+		let current = if options.device_max { (options.max_volume as f64 / MAX_NATURAL_VOL as f64 * 100.0).round() as u32 } else { 0 };
+		let choices = std::iter::once(0).chain(MAX_VOLUME_CHOICES.iter().copied());
+
+		for percent in choices {
+			let button = gtk::ModelButton::new();
+			button.set_property_role(gtk::ButtonRole::Radio);
+			button.set_property_active(percent == current);
+			button.set_property_text(Some(&if percent == 0 { "Default".to_owned() } else { format!("{}%", percent) }));
+
+			let name = data.name.clone();
+			let root = root.clone();
+			let trigger = trigger.clone();
+			button.connect_clicked(move |_| {
+				if let Some(actions) = trigger.get_action_group("app") {
+					actions.activate_action("device_max_volume", Some(&format!("{}:{}", percent, name).to_variant()));
+				}
+				root.popdown();
+			});
+
+			menu.add(&button);
+		}
+	}
+
+
+	/**
+	 * Shows or hides the badge over a meter's icon.
+	 *
+	 * * `widgets` - The meter's widgets.
+	 * * `badge`   - The icon name and tooltip of the badge, or None to hide it.
+	 */
+
+	pub fn set_badge(widgets: &MeterWidgets, badge: Option<( &str, &str )>) {
+		match badge {
+			Some(( icon, tooltip )) => {
+				widgets.badge.set_from_icon_name(Some(icon), gtk::IconSize::Menu);
+				widgets.badge.set_tooltip_text(Some(tooltip));
+				widgets.badge.show();
+			},
+			None => widgets.badge.hide()
+		}
+	}
+
+
 	/**
 	 * Initializes all of the Widgets to make a meter, and returns them.
 	 */
@@ -224,17 +666,40 @@ impl dyn Meter {
 		label.set_line_wrap(true);
 		label.set_lines(2);
 
+		// A small icon over the corner of the meter's icon, which indicates a special state.
+		let badge = gtk::Image::new();
+		badge.set_halign(gtk::Align::End);
+		badge.set_valign(gtk::Align::End);
+		badge.set_no_show_all(true);
+
+		let icon_overlay = gtk::Overlay::new();
+		icon_overlay.set_halign(gtk::Align::Center);
+		icon_overlay.add(&icon);
+		icon_overlay.add_overlay(&badge);
+
 		label_container.pack_end(&label, false, true, 0);
-		label_container.pack_end(&icon, false, false, 3);
+		label_container.pack_end(&icon_overlay, false, false, 3);
 
 		let select = gtk::Button::new();
 		select.set_widget_name("app_select");
 		select.get_style_context().add_class("flat");
+		select.set_no_show_all(true);
+
+		let select_label = gtk::Label::new(None);
+		select_label.set_widget_name("app_label");
+		select_label.set_ellipsize(pango::EllipsizeMode::End);
+		select_label.set_max_width_chars(8);
+		select_label.show();
+		select.add(&select_label);
 
 		let scales_outer = gtk::Box::new(gtk::Orientation::Horizontal, 0);
 		let scales_inner = gtk::Box::new(gtk::Orientation::Horizontal, 0);
 		scales_outer.pack_start(&scales_inner, true, false, 0);
 
+		let balance_outer = gtk::Box::new(gtk::Orientation::Vertical, 0);
+		let balance_inner = gtk::Box::new(gtk::Orientation::Vertical, 0);
+		balance_outer.pack_start(&balance_inner, true, true, 0);
+
 		let status_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
 		let status_icon = gtk::Image::from_icon_name(Some("audio-volume-muted-symbolic"), gtk::IconSize::Button);
 		
@@ -248,22 +713,88 @@ impl dyn Meter {
 		status.get_style_context().add_class("muted");
 
 		root.pack_end(&status_box, false, false, 3);
+		root.pack_end(&balance_outer, false, false, 0);
 		root.pack_end(&scales_outer, true, true, 2);
 		root.pack_start(&app_button, false, false, 0);
+		root.pack_start(&select, false, false, 0);
 
 		MeterWidgets {
 			root,
 			
 			icon,
+			badge,
 			label,
 			select,
+			select_label,
 			app_button,
 			
 			status,
 			status_icon,
 
 			scales_outer,
-			scales_inner
+			scales_inner,
+
+			balance_outer,
+			balance_inner
 		}
 	}
 }
+
+
+
+#[cfg(test)]
+mod tests {
+	use libpulse::volume::Volume;
+
+	use super::{ Meter, ScaleOptions, MAX_NATURAL_VOL };
+	use super::super::volume::{ VolumeCurve, format_db };
+
+	/** Scale options topping out at 100%, 150% and 200%. */
+	fn options() -> Vec<ScaleOptions> {
+		[ 100, 150, 200 ].iter().map(|percent| ScaleOptions { max_volume: MAX_NATURAL_VOL * percent / 100,
+			..ScaleOptions::default() }).collect()
+	}
+
+
+	#[test]
+	fn full_peak_at_the_max_volume_fills_the_scale() {
+		for options in options() {
+			let fill = Meter::peak_fill(options, Volume(options.max_volume), MAX_NATURAL_VOL);
+			assert!((fill - options.max_volume as f64).abs() < 1e-6, "{:?}: {}", options, fill);
+		}
+	}
+
+
+	#[test]
+	fn full_peak_fills_up_to_the_volume() {
+		for options in options() {
+			let natural = Volume(MAX_NATURAL_VOL);
+			let fill = Meter::peak_fill(options, natural, MAX_NATURAL_VOL);
+			assert!((fill - Meter::scale_value(options, natural)).abs() < 1e-6, "{:?}: {}", options, fill);
+			assert!(fill <= options.max_volume as f64 + 1e-6, "{:?}: {}", options, fill);
+		}
+	}
+
+
+	#[test]
+	fn peak_fill_is_proportional_to_the_peak() {
+		for options in options() {
+			let volume = Volume(options.max_volume);
+			assert_eq!(Meter::peak_fill(options, volume, 0), 0.0);
+			let half = Meter::peak_fill(options, volume, MAX_NATURAL_VOL / 2);
+			assert!((half - options.max_volume as f64 / 2.0).abs() < 1e-6, "{:?}: {}", options, half);
+		}
+	}
+
+
+	#[test]
+	fn status_label_is_clamped_to_the_max_volume() {
+		let above = Volume(MAX_NATURAL_VOL * 3 / 2);
+		let labels: Vec<String> = options().into_iter().map(|options| Meter::status_label(options, above)).collect();
+		assert_eq!(labels, vec![ "100%", "150%", "150%" ]);
+
+		let decibel = ScaleOptions { curve: VolumeCurve::Decibel, max_volume: MAX_NATURAL_VOL, ..ScaleOptions::default() };
+		assert_eq!(Meter::status_label(decibel, above), format_db(Volume(MAX_NATURAL_VOL)));
+		assert_eq!(Meter::status_label(decibel, Volume::MUTED), format_db(Volume::MUTED));
+	}
+}
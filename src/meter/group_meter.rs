@@ -0,0 +1,425 @@
+/*!
+ * A meter for representing the streams of one application together.
+ */
+
+use gtk::prelude::*;
+use libpulse::volume::{ Volume, ChannelVolumes };
+
+use crate::shared::Shared;
+use crate::backend::{ Backend, StreamType, StreamData };
+use crate::hidden::Filter;
+use super::base_meter::{ Meter, MeterWidgets, MeterData, ScaleOptions };
+use super::volume::{ format_percent, format_db };
+use super::base_meter::{ MAX_NATURAL_VOL, INPUT_ICONS, OUTPUT_ICONS };
+
+
+/**
+ * Gets the key that a stream is grouped by, which identifies its application.
+ * Streams are grouped by their application name, so that the streams of every process of an application are grouped,
+ * or by their process id and the host it runs on if they don't have one.
+ * Returns None for streams that don't identify their application, which aren't grouped.
+ *
+ * * `stream` - The stream to get the key of.
+ */
+
+pub fn group_key(stream: &StreamData) -> Option<String> {
+	if !stream.app.name.is_empty() { return Some(format!("name:{}", stream.app.name)); }
+	if !stream.app.process_id.is_empty() { return Some(format!("process:{}@{}", stream.app.process_id, stream.app.process_host)); }
+	None
+}
+
+
+/**
+ * Gets a member stream's new volumes when the group's scale is moved, keeping the member's balance,
+ * and its volume relative to the loudest member's, which the scale shows.
+ * If every member is silent, there's no mix to keep, so the member is set to the new volume.
+ *
+ * * `volumes`   - The member's current volumes.
+ * * `group_max` - The loudest member's volume.
+ * * `volume`    - The volume the scale was moved to.
+ */
+
+pub fn scale_member(mut volumes: ChannelVolumes, group_max: Volume, volume: Volume) -> ChannelVolumes {
+	let member = if group_max.0 == 0 { volume.0 as u64 }
+		else { volumes.max().0 as u64 * volume.0 as u64 / group_max.0 as u64 };
+	volumes.scale(Volume(member.min(Volume::MAX.0 as u64) as u32));
+	volumes
+}
+
+
+/**
+ * A meter widget representing the streams of an application.
+ * Controls the volume and mute state of all of its member streams together,
+ * and has an expander that reveals the individual streams' meters, which are packed into `members_box`.
+ */
+
+pub struct GroupMeter {
+	pub widget: gtk::Box,
+	pub members_box: gtk::Box,
+
+	data: MeterData,
+	widgets: MeterWidgets,
+	pulse: Shared<dyn Backend>,
+
+	/** The indices of the member streams, shared with the widgets' signal handlers. */
+	members: Shared<Vec<u32>>,
+
+	options: ScaleOptions,
+	pub peak: Option<Vec<u32>>,
+}
+
+impl GroupMeter {
+
+	/**
+	 * Creates a new GroupMeter, which is collapsed.
+	 *
+	 * * `pulse` - The pulse store to bind events to.
+	 * * `t`     - The type of the member streams.
+	 */
+
+	pub fn new(pulse: Shared<dyn Backend>, t: StreamType) -> Self {
+		let widgets = Meter::build_meter();
+
+		let widget = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+		widget.pack_start(&widgets.root, false, false, 0);
+
+		let members_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+
+		let revealer = gtk::Revealer::new();
+		revealer.set_transition_type(gtk::RevealerTransitionType::SlideRight);
+		revealer.add(&members_box);
+		widget.pack_start(&revealer, false, false, 0);
+
+		// The device button of the meter is used as the expander, showing the number of streams.
+		let select_icon = gtk::Image::from_icon_name(Some("pan-end-symbolic"), gtk::IconSize::Menu);
+		let select_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+		widgets.select.remove(&widgets.select_label);
+		select_box.pack_start(&widgets.select_label, true, true, 0);
+		select_box.pack_start(&select_icon, false, false, 0);
+		select_box.show_all();
+		widgets.select.add(&select_box);
+		widgets.select.set_tooltip_text(Some("Show Streams"));
+		widgets.select.show();
+
+		widgets.select.connect_clicked(move |select| {
+			let expanded = !revealer.get_reveal_child();
+			revealer.set_reveal_child(expanded);
+			select_icon.set_from_icon_name(Some(if expanded { "pan-start-symbolic" } else { "pan-end-symbolic" }), gtk::IconSize::Menu);
+			select.set_tooltip_text(Some(if expanded { "Hide Streams" } else { "Show Streams" }));
+		});
+
+		let members = Shared::new(Vec::new());
+
+		let pulse_clone = pulse.clone();
+		let members_clone = members.clone();
+		widgets.status.connect_clicked(move |status| {
+			let mute = !status.get_style_context().has_class("muted");
+			let pulse = pulse_clone.borrow_mut();
+			for index in members_clone.borrow().iter() { pulse.set_muted(t, *index, mute); }
+		});
+
+		let pulse_clone = pulse.clone();
+		let members_clone = members.clone();
+		widgets.app_button.connect_clicked(move |trigger| {
+			GroupMeter::show_popup(trigger, &pulse_clone, t, &members_clone.borrow());
+		});
+
+		let mut meter = Self {
+			widget,
+			members_box,
+
+			data: MeterData { t, ..MeterData::default() },
+			widgets,
+			pulse,
+			members,
+
+			options: ScaleOptions::default(),
+			peak: None
+		};
+
+		meter.rebuild_widgets();
+		meter
+	}
+
+
+	/**
+	 * Rebuilds the scale, which sets the volume of every member stream, keeping the balance of each one and their mix.
+	 */
+
+	fn rebuild_widgets(&mut self) {
+		let scales = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+		let scale = Meter::build_scale(self.options);
+
+		let t = self.data.t;
+		let options = self.options;
+		let pulse = self.pulse.clone();
+		let members = self.members.clone();
+		scale.connect_change_value(move |_, _, value| {
+			let pulse = pulse.borrow_mut();
+			let streams: Vec<( u32, ChannelVolumes )> = members.borrow().iter()
+				.filter_map(|index| pulse.store().get_stream(t, *index).map(|stream| ( *index, stream.data.volume ))).collect();
+			let group_max = streams.iter().map(|( _, volumes )| volumes.max()).max_by_key(|volume| volume.0).unwrap_or(Volume::MUTED);
+
+			for ( index, volumes ) in streams {
+				let volumes = scale_member(volumes, group_max, Meter::scale_volume(options, value));
+				pulse.set_volume(t, index, volumes);
+				if volumes.max().0 > 0 { pulse.set_muted(t, index, false); }
+			}
+			gtk::Inhibit(false)
+		});
+
+		scales.pack_start(&scale, false, false, 0);
+		scales.show_all();
+
+		self.widgets.scales_outer.remove(&self.widgets.scales_inner);
+		self.widgets.scales_outer.pack_start(&scales, true, false, 0);
+		self.widgets.scales_inner = scales;
+		self.update_widgets();
+	}
+
+
+	/**
+	 * Sets the member streams of the group, and refreshes the meter to show them.
+	 * The meter shows the loudest member's volume, and is muted if every member is.
+	 *
+	 * * `streams` - The member streams.
+	 */
+
+	pub fn set_members(&mut self, streams: &[&StreamData]) {
+		let mut indices: Vec<u32> = streams.iter().map(|stream| stream.data.index).collect();
+		indices.sort_unstable();
+		if *self.members.borrow() != indices { self.members.replace(indices); }
+
+		let first = match streams.iter().min_by_key(|stream| stream.data.index) { Some(first) => first, None => return };
+		let loudest = streams.iter().max_by_key(|stream| stream.data.volume.max().0).unwrap_or(first);
+
+		let mut data = loudest.data.clone();
+		data.index = first.data.index;
+		data.icon = first.data.icon.clone();
+		data.description = if first.app.name.is_empty() { first.data.description.clone() } else { first.app.name.clone() };
+		data.muted = streams.iter().all(|stream| stream.data.muted);
+		self.set_data(&data);
+
+		let count = format!("{} Streams", streams.len());
+		if self.widgets.select_label.get_text() != count { self.widgets.select_label.set_label(&count); }
+	}
+
+
+	/**
+	 * Updates the scale and the status button to reflect the current volume level.
+	 */
+
+	fn update_widgets(&mut self) {
+		let volume = self.data.volume.max();
+		if let Some(scale) = self.widgets.scales_inner.get_children().first() {
+			let scale = scale.clone().downcast::<gtk::Scale>().expect("Scales box has non-scale children.");
+			scale.set_value(if self.data.muted { 0.0 } else { Meter::scale_value(self.options, volume) });
+			scale.set_tooltip_text(Some(&format!("{} ({})", format_percent(volume), format_db(volume))));
+		}
+
+		let status_vol = if self.data.muted { 0 } else { volume.0 };
+
+		let &icons = if self.data.t == StreamType::SinkInput { &OUTPUT_ICONS } else { &INPUT_ICONS };
+
+		self.widgets.status_icon.set_from_icon_name(Some(icons[
+			if status_vol == 0 { 0 } else if status_vol >= MAX_NATURAL_VOL { 3 }
+			else if status_vol >= MAX_NATURAL_VOL / 2 { 2 } else { 1 }]), gtk::IconSize::Button);
+
+		let status_text = Meter::status_label(self.options, Volume(status_vol));
+		self.widgets.status.set_label(&status_text);
+
+		if status_vol == 0 { self.widgets.status.get_style_context().add_class("muted") }
+		else { self.widgets.status.get_style_context().remove_class("muted") }
+	}
+
+
+	/**
+	 * Shows a popup menu on the top button, with items to move every member stream
+	 * to a different output or input device, and to hide the application's streams.
+	 */
+
+	fn show_popup(trigger: &gtk::Button, pulse_shr: &Shared<dyn Backend>, t: StreamType, members: &[u32]) {
+		let pulse = pulse_shr.borrow_mut();
+		let streams: Vec<&StreamData> = members.iter().filter_map(|index| pulse.store().get_stream(t, *index)).collect();
+		let first = match streams.first() { Some(first) => first, None => return };
+
+		// A device is only shown as the current one if every member stream is connected to it.
+		let current = Some(first.monitor_index).filter(|current| streams.iter().all(|stream| stream.monitor_index == *current));
+		let filter = Filter::for_stream(t, &first.data.name, &first.app);
+
+		let root = gtk::PopoverMenu::new();
+		root.set_border_width(6);
+
+		let menu = gtk::Box::new(gtk::Orientation::Vertical, 0);
+		menu.set_size_request(132, -1);
+		root.add(&menu);
+
+		let ( title, devices ) = if t == StreamType::SinkInput { ( "Output Device", &pulse.store().sinks ) }
+			else { ( "Input Device", &pulse.store().sources ) };
+
+		let mut devices: Vec<( &u32, &StreamData )> = devices.iter().collect();
+		devices.sort_by(|( _, a ), ( _, b )| a.data.description.cmp(&b.data.description));
+
+		let label = gtk::Label::new(Some(title));
+		label.set_sensitive(false);
+		menu.pack_start(&label, true, true, 3);
+
+		for (i, v) in devices {
+			let button = gtk::ModelButton::new();
+			button.set_property_role(gtk::ButtonRole::Radio);
+			button.set_property_active(Some(*i) == current);
+			let button_label = gtk::Label::new(Some(&v.data.description));
+			button_label.set_ellipsize(pango::EllipsizeMode::End);
+			button_label.set_max_width_chars(18);
+			button.get_child().unwrap().downcast::<gtk::Box>().unwrap().add(&button_label);
+
+			let i = *i;
+			let root = root.clone();
+			let members = members.to_vec();
+			let pulse_clone = pulse_shr.clone();
+			button.connect_clicked(move |_| {
+				let pulse = pulse_clone.borrow_mut();
+				for index in &members { pulse.move_stream(t, *index, i); }
+				root.popdown();
+			});
+
+			menu.add(&button);
+		}
+
+		menu.pack_start(&gtk::SeparatorMenuItem::new(), false, false, 3);
+
+		let hide = gtk::ModelButton::new();
+		hide.set_property_text(Some("Hide These Streams"));
+		let pulse_clone = pulse_shr.clone();
+		hide.connect_clicked(move |_| pulse_clone.borrow_mut().store_mut().hide_list.add(filter.clone()));
+		menu.add(&hide);
+
+		for child in &root.get_children() { child.show_all(); }
+		root.set_relative_to(Some(trigger));
+		root.popup();
+	}
+}
+
+impl Meter for GroupMeter {
+	fn get_index(&self) -> u32 {
+		self.data.index
+	}
+
+	/** The group's scale controls whole streams, so its channels are never split. */
+	fn split_channels(&mut self, _: bool) {}
+
+	fn set_scale_options(&mut self, options: ScaleOptions) {
+		if self.options == options { return }
+		self.options = options;
+		self.rebuild_widgets();
+	}
+
+	fn set_data(&mut self, data: &MeterData) {
+		if data.icon != self.data.icon {
+			self.data.icon = data.icon.clone();
+			self.widgets.icon.set_from_icon_name(Some(&self.data.icon), gtk::IconSize::Dnd);
+		}
+
+		if data.description != self.data.description {
+			self.data.description = data.description.clone();
+			self.widgets.label.set_label(&self.data.description);
+			self.widgets.app_button.set_tooltip_text(Some(&self.data.description));
+		}
+
+		self.data.index = data.index;
+		if data.volume != self.data.volume || data.muted != self.data.muted {
+			self.data.volume = data.volume;
+			self.data.muted = data.muted;
+			self.update_widgets();
+		}
+	}
+
+	fn set_peak(&mut self, peak: Option<Vec<u32>>) {
+		self.peak = peak;
+
+		if let Some(scale) = self.widgets.scales_inner.get_children().first() {
+			let scale = scale.clone().downcast::<gtk::Scale>().expect("Scales box has non-scale children.");
+			match &self.peak {
+				Some(peaks) => {
+					let peak = peaks.iter().copied().max().unwrap_or(0);
+					let peak_scaled = Meter::peak_fill(self.options, self.data.volume.max(), peak);
+					scale.set_fill_level(peak_scaled);
+					scale.set_show_fill_level(!self.data.muted && peak_scaled > 0.5);
+					scale.get_style_context().add_class("visualizer");
+				},
+				None => {
+					scale.set_show_fill_level(false);
+					scale.get_style_context().remove_class("visualizer");
+				}
+			}
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use libpulse::volume::{ Volume, ChannelVolumes };
+
+	use crate::backend::StreamData;
+	use crate::rules::AppProps;
+	use crate::meter::MAX_NATURAL_VOL;
+	use super::{ group_key, scale_member };
+
+
+	/**
+	 * Creates a stream of the application and process specified.
+	 */
+
+	fn stream(name: &str, process_id: &str, process_host: &str) -> StreamData {
+		let app = AppProps { name: name.to_owned(), process_id: process_id.to_owned(), process_host: process_host.to_owned(), ..AppProps::default() };
+		StreamData { app, ..StreamData::default() }
+	}
+
+
+	#[test]
+	fn processes_of_an_application_are_grouped() {
+		assert_eq!(group_key(&stream("Firefox", "100", "laptop")), group_key(&stream("Firefox", "200", "laptop")));
+		assert_ne!(group_key(&stream("Firefox", "100", "laptop")), group_key(&stream("Chromium", "100", "laptop")));
+	}
+
+
+	#[test]
+	fn unnamed_streams_are_grouped_by_process_and_host() {
+		assert_eq!(group_key(&stream("", "100", "laptop")), group_key(&stream("", "100", "laptop")));
+		assert_ne!(group_key(&stream("", "100", "laptop")), group_key(&stream("", "100", "desktop")));
+		assert_ne!(group_key(&stream("", "100", "laptop")), group_key(&stream("", "200", "laptop")));
+		assert_eq!(group_key(&stream("", "", "laptop")), None);
+	}
+
+
+	/**
+	 * Creates stereo volumes, with the channels at the percentages specified.
+	 */
+
+	fn volumes(left: u32, right: u32) -> ChannelVolumes {
+		let mut volumes = ChannelVolumes::default();
+		volumes.set_len(2);
+		volumes.get_mut()[0] = Volume(MAX_NATURAL_VOL * left / 100);
+		volumes.get_mut()[1] = Volume(MAX_NATURAL_VOL * right / 100);
+		volumes
+	}
+
+
+	#[test]
+	fn members_keep_their_mix() {
+		let group_max = Volume(MAX_NATURAL_VOL);
+		let half = Volume(MAX_NATURAL_VOL / 2);
+		assert_eq!(scale_member(volumes(100, 100), group_max, half), volumes(50, 50));
+		assert_eq!(scale_member(volumes(50, 50), group_max, half), volumes(25, 25));
+		assert_eq!(scale_member(volumes(50, 25), group_max, Volume(MAX_NATURAL_VOL * 2)), volumes(100, 50));
+		assert_eq!(scale_member(volumes(0, 0), group_max, half), volumes(0, 0));
+	}
+
+
+	#[test]
+	fn silent_groups_are_set_to_the_volume() {
+		let half = Volume(MAX_NATURAL_VOL / 2);
+		assert_eq!(scale_member(volumes(0, 0), Volume::MUTED, half), volumes(50, 50));
+	}
+}
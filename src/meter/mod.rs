@@ -5,6 +5,9 @@
 mod base_meter;
 pub use base_meter::*;
 
+mod volume;
+pub use volume::*;
+
 mod sink_meter;
 pub use sink_meter::*;
 
@@ -13,3 +16,6 @@ pub use source_meter::*;
 
 mod stream_meter;
 pub use stream_meter::*;
+
+mod group_meter;
+pub use group_meter::*;
@@ -3,12 +3,14 @@
  */
 
 use gtk::prelude::*;
+use gio::prelude::*;
+use libpulse::volume::Volume;
 use glib::translate::{ ToGlib, FromGlib };
 
-use crate::pulse::Pulse;
+use crate::backend::{ Backend, DeviceState, VIRTUAL_DEVICE_PREFIX };
 use crate::shared::Shared;
-use super::base_meter::{ Meter, MeterWidgets, MeterData };
-use super::base_meter::{ MAX_NATURAL_VOL, MAX_SCALE_VOL, OUTPUT_ICONS };
+use super::base_meter::{ Meter, MeterWidgets, MeterData, ScaleOptions };
+use super::base_meter::{ MAX_NATURAL_VOL, OUTPUT_ICONS };
 
 
 /**
@@ -21,10 +23,11 @@ pub struct SinkMeter {
 
 	data: MeterData,
 	widgets: MeterWidgets,
-	pulse: Shared<Pulse>,
+	pulse: Shared<dyn Backend>,
 
 	pub split: bool,
-	pub peak: Option<u32>,
+	options: ScaleOptions,
+	pub peak: Option<Vec<u32>>,
 
 	l_id: Option<glib::signal::SignalHandlerId>,
 	s_id: Option<glib::signal::SignalHandlerId>,
@@ -36,7 +39,7 @@ impl SinkMeter {
 	 * Creates a new SinkMeter.
 	 */
 
-	pub fn new(pulse: Shared<Pulse>) -> Self {
+	pub fn new(pulse: Shared<dyn Backend>) -> Self {
 		let widgets = Meter::build_meter();
 		Self {
 			widget: widgets.root.clone(),
@@ -45,7 +48,7 @@ impl SinkMeter {
 			widgets,
 			data: MeterData::default(),
 
-			split: false, peak: None, s_id: None, l_id: None
+			split: false, options: ScaleOptions::default(), peak: None, s_id: None, l_id: None
 		}
 	}
 
@@ -56,10 +59,15 @@ impl SinkMeter {
 	 */
 
 	fn rebuild_widgets(&mut self) {
-		let scales = Meter::build_scales(&self.pulse, &self.data, self.split);
+		let scales = Meter::build_scales(&self.pulse, &self.data, self.split, self.options);
 		self.widgets.scales_outer.remove(&self.widgets.scales_inner);
 		self.widgets.scales_outer.pack_start(&scales, true, false, 0);
 		self.widgets.scales_inner = scales;
+
+		let balance = Meter::build_balance(&self.pulse, &self.data, self.split);
+		self.widgets.balance_outer.remove(&self.widgets.balance_inner);
+		self.widgets.balance_outer.pack_start(&balance, true, true, 0);
+		self.widgets.balance_inner = balance;
 		self.update_widgets();
 
 		let t 		= self.data.t;
@@ -74,36 +82,64 @@ impl SinkMeter {
 
 		let pulse = self.pulse.clone();
 		let index	= self.data.index;
+		let options = self.options;
 
 		if self.l_id.is_some() { self.widgets.app_button.disconnect(
 			glib::signal::SignalHandlerId::from_glib(self.l_id.as_ref().unwrap().to_glib())) }
 		self.l_id = Some(self.widgets.app_button.connect_clicked(move |trigger| {
-			SinkMeter::show_popup(&trigger, &pulse, index);
+			SinkMeter::show_popup(&trigger, &pulse, index, options);
 		}));
 	}
 
 
 	/**
-	 * Updates each scale widget to reflect the current volume level.
+	 * Updates each scale widget and the status button to reflect the current volume level.
 	 */
 
 	fn update_widgets(&mut self) {
-		for (i, v) in self.data.volume.get().iter().enumerate() {
+		let volumes = if self.split { self.data.volume.get().to_vec() } else { vec![ self.data.volume.max() ] };
+		for (i, v) in volumes.iter().enumerate() {
 			if let Some(scale) = self.widgets.scales_inner.get_children().get(i) {
 				let scale = scale.clone().downcast::<gtk::Scale>().expect("Scales box has non-scale children.");
 				scale.set_sensitive(!self.data.muted);
-				scale.set_value(v.0 as f64);
+				scale.set_value(Meter::scale_value(self.options, *v));
+				scale.set_tooltip_text(Some(&Meter::scale_tooltip(&self.data, if self.split { Some(i) } else { None })));
 			}
 		}
+
+		Meter::update_balance(&self.widgets.balance_inner, &self.data);
+
+		let status_vol = if self.data.muted { 0 } else { self.data.volume.max().0 };
+
+		self.widgets.status_icon.set_from_icon_name(Some(OUTPUT_ICONS[
+			if status_vol == 0 { 0 } else if status_vol >= MAX_NATURAL_VOL { 3 }
+			else if status_vol >= MAX_NATURAL_VOL / 2 { 2 } else { 1 }]), gtk::IconSize::Button);
+
+		let status_text = Meter::status_label(self.options, Volume(status_vol));
+		self.widgets.status.set_label(&status_text);
+		self.widgets.status.set_tooltip_text(Some(&Meter::volume_tooltip(&self.data)));
+
+		if status_vol == 0 {self.widgets.status.get_style_context().add_class("muted") }
+		else { self.widgets.status.get_style_context().remove_class("muted") }
+	}
+
+
+	/**
+	 * Shows a badge if the sink is suspended.
+	 */
+
+	fn update_badge(&self) {
+		Meter::set_badge(&self.widgets,
+			if self.data.state == Some(DeviceState::Suspended) { Some(( "media-playback-pause-symbolic", "Suspended" )) } else { None });
 	}
 
 
 	/**
 	 * Shows a popup menu on the top button, with items to set
-	 * the Sink as default, and change the visible sink.
+	 * the Sink as default, test its speakers, suspend it, change its port and maximum volume, remove it if it is virtual, and change the visible sink.
 	 */
 
-	fn show_popup(trigger: &gtk::Button, pulse_shr: &Shared<Pulse>, index: u32) {
+	fn show_popup(trigger: &gtk::Button, pulse_shr: &Shared<dyn Backend>, index: u32, options: ScaleOptions) {
 		let pulse = pulse_shr.borrow_mut();
 		let root = gtk::PopoverMenu::new();
 		root.set_border_width(6);
@@ -120,8 +156,8 @@ impl SinkMeter {
 		let set_default = gtk::ModelButton::new();
 		set_default.set_property_role(gtk::ButtonRole::Check);
 		set_default.set_property_text(Some("Set as Default"));
-		set_default.set_property_active(pulse.default_sink == index);
-		set_default.set_sensitive(pulse.default_sink != index);
+		set_default.set_property_active(pulse.store().default_sink == index);
+		set_default.set_sensitive(pulse.store().default_sink != index);
 			
 		let pulse_clone = pulse_shr.clone();
 		set_default.connect_clicked(move |set_default| {
@@ -131,14 +167,43 @@ impl SinkMeter {
 		});
 		menu.add(&set_default);
 
-		if pulse.sinks.len() >= 2 {
+		if let Some(device) = pulse.store().sinks.get(&index) {
+			let speaker_test = gtk::ModelButton::new();
+			speaker_test.set_property_text(Some("Test Speakers..."));
+			let root_clone = root.clone();
+			let trigger_clone = trigger.clone();
+			speaker_test.connect_clicked(move |_| {
+				if let Some(actions) = trigger_clone.get_action_group("app") {
+					actions.activate_action("speaker_test", Some(&index.to_variant()));
+				}
+				root_clone.popdown();
+			});
+			menu.add(&speaker_test);
+
+			Meter::add_suspend_item(&menu, pulse_shr, &device.data);
+			Meter::add_port_items(&menu, &root, pulse_shr, &device.data);
+			Meter::add_max_volume_items(&menu, &root, trigger, &device.data, options);
+
+			// Virtual devices created by Myxer can be removed by unloading the module that created them.
+			if let Some(module) = device.owner_module.filter(|_| device.data.name.starts_with(VIRTUAL_DEVICE_PREFIX)) {
+				menu.pack_start(&gtk::SeparatorMenuItem::new(), false, false, 3);
+
+				let remove = gtk::ModelButton::new();
+				remove.set_property_text(Some("Remove Device"));
+				let pulse_clone = pulse_shr.clone();
+				remove.connect_clicked(move |_| pulse_clone.borrow().unload_module(module));
+				menu.add(&remove);
+			}
+		}
+
+		if pulse.store().sinks.len() >= 2 {
 			menu.pack_start(&gtk::SeparatorMenuItem::new(), false, false, 3);
 
 			let label = gtk::Label::new(Some("Visible Output"));
 			label.set_sensitive(false);
 			menu.pack_start(&label, true, true, 3);
 			
-			for (i, v) in &pulse.sinks {
+			for (i, v) in &pulse.store().sinks {
 				let button = gtk::ModelButton::new();
 				button.set_property_role(gtk::ButtonRole::Radio);
 				button.set_property_active(v.data.index == index);
@@ -176,13 +241,21 @@ impl Meter for SinkMeter {
 		self.rebuild_widgets();
 	}
 
+	fn set_scale_options(&mut self, options: ScaleOptions) {
+		if self.options == options { return }
+		self.options = options;
+		self.rebuild_widgets();
+	}
+
 	fn set_data(&mut self, data: &MeterData) {
 		let volume_old = self.data.volume;
 		let volume_changed = data.volume != volume_old;
 
-		if data.t != self.data.t || data.index != self.data.index || data.volume.len() != self.data.volume.len() {
+		if data.t != self.data.t || data.index != self.data.index || data.volume.len() != self.data.volume.len()
+			|| data.channel_map != self.data.channel_map {
 			self.data.t = data.t;
 			self.data.volume = data.volume;
+			self.data.channel_map = data.channel_map;
 			self.data.index = data.index;
 			self.rebuild_widgets();
 		}
@@ -200,41 +273,30 @@ impl Meter for SinkMeter {
 		if data.description != self.data.description {
 			self.data.description = data.description.clone();
 			self.widgets.label.set_label(&self.data.description);
-			self.widgets.app_button.set_tooltip_text(Some(&self.data.description));
+			self.widgets.app_button.set_tooltip_text(Some(&Meter::device_tooltip(&self.data)));
+		}
+
+		if data.state != self.data.state {
+			self.data.state = data.state;
+			self.widgets.app_button.set_tooltip_text(Some(&Meter::device_tooltip(&self.data)));
+			self.update_badge();
 		}
 
 		if volume_changed || data.muted != self.data.muted {
 			self.data.volume = data.volume;
 			self.data.muted = data.muted;
 			self.update_widgets();
-
-			let status_vol = if self.data.muted { 0 } else { self.data.volume.max().0 };
-
-			self.widgets.status_icon.set_from_icon_name(Some(OUTPUT_ICONS[
-				if status_vol == 0 { 0 } else if status_vol >= MAX_NATURAL_VOL { 3 }
-				else if status_vol >= MAX_NATURAL_VOL / 2 { 2 } else { 1 }]), gtk::IconSize::Button);
-
-			let mut vol_scaled = ((status_vol as f64) / MAX_NATURAL_VOL as f64 * 100.0).round() as u8;
-			if vol_scaled > 150 { vol_scaled = 150 }
-
-			let mut string = vol_scaled.to_string();
-			string.push('%');
-
-			self.widgets.status.set_label(&string);
-
-			if status_vol == 0 {self.widgets.status.get_style_context().add_class("muted") }
-			else { self.widgets.status.get_style_context().remove_class("muted") }
 		}
 	}
 
-	fn set_peak(&mut self, peak: Option<u32>) {
+	fn set_peak(&mut self, peak: Option<Vec<u32>>) {
 		self.peak = peak;
 
-		if self.peak.is_some() {
-			for (i, s) in self.widgets.scales_inner.get_children().iter().enumerate() {
+		if let Some(peaks) = &self.peak {
+			let fills = Meter::peak_fills(&self.data, peaks, self.split, self.options);
+			for (s, peak_scaled) in self.widgets.scales_inner.get_children().iter().zip(fills) {
 				let s = s.clone().downcast::<gtk::Scale>().expect("Scales box has non-scale children.");
-				let peak_scaled = self.peak.unwrap() as f64 * (self.data.volume.get()[i].0 as f64 / MAX_SCALE_VOL as f64);
-				s.set_fill_level(peak_scaled as f64);
+				s.set_fill_level(peak_scaled);
 				s.set_show_fill_level(!self.data.muted && peak_scaled > 0.5);
 				s.get_style_context().add_class("visualizer");
 			}
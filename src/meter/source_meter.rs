@@ -3,12 +3,19 @@
  */
 
 use gtk::prelude::*;
+use libpulse::volume::Volume;
 use glib::translate::{ ToGlib, FromGlib };
 
-use crate::pulse::Pulse;
+use crate::backend::{ Backend, DeviceState };
 use crate::shared::Shared;
-use super::base_meter::{ Meter, MeterWidgets, MeterData };
-use super::base_meter::{ MAX_NATURAL_VOL, MAX_SCALE_VOL, INPUT_ICONS };
+use super::base_meter::{ Meter, MeterWidgets, MeterData, ScaleOptions };
+use super::base_meter::{ MAX_NATURAL_VOL, INPUT_ICONS };
+
+/** The latencies that can be chosen for listening to a source, in milliseconds. */
+const LOOPBACK_LATENCIES: [u32; 4] = [ 20, 50, 100, 200 ];
+
+/** The latency used when listening to a source is started without choosing one. */
+const DEFAULT_LOOPBACK_LATENCY: u32 = 50;
 
 
 /**
@@ -21,10 +28,12 @@ pub struct SourceMeter {
 
 	data: MeterData,
 	widgets: MeterWidgets,
-	pulse: Shared<Pulse>,
+	pulse: Shared<dyn Backend>,
 
 	split: bool,
-	peak: Option<u32>,
+	options: ScaleOptions,
+	peak: Option<Vec<u32>>,
+	loopback: bool,
 
 	l_id: Option<glib::signal::SignalHandlerId>,
 	s_id: Option<glib::signal::SignalHandlerId>,
@@ -36,7 +45,7 @@ impl SourceMeter {
 	 * Creates a new SourceMeter.
 	 */
 
-	pub fn new(pulse: Shared<Pulse>) -> Self {
+	pub fn new(pulse: Shared<dyn Backend>) -> Self {
 		let widgets = Meter::build_meter();
 
 		Self {
@@ -46,7 +55,7 @@ impl SourceMeter {
 			widgets,
 			data: MeterData::default(),
 
-			split: false, peak: None, l_id: None, s_id: None
+			split: false, options: ScaleOptions::default(), peak: None, loopback: false, l_id: None, s_id: None
 		}
 	}
 
@@ -57,10 +66,15 @@ impl SourceMeter {
 	 */
 
 	fn rebuild_widgets(&mut self) {
-		let scales = Meter::build_scales(&self.pulse, &self.data, self.split);
+		let scales = Meter::build_scales(&self.pulse, &self.data, self.split, self.options);
 		self.widgets.scales_outer.remove(&self.widgets.scales_inner);
 		self.widgets.scales_outer.pack_start(&scales, true, false, 0);
 		self.widgets.scales_inner = scales;
+
+		let balance = Meter::build_balance(&self.pulse, &self.data, self.split);
+		self.widgets.balance_outer.remove(&self.widgets.balance_inner);
+		self.widgets.balance_outer.pack_start(&balance, true, true, 0);
+		self.widgets.balance_inner = balance;
 		self.update_widgets();
 
 		let t 		= self.data.t;
@@ -76,36 +90,180 @@ impl SourceMeter {
 
 		let pulse = self.pulse.clone();
 		let index = self.data.index;
+		let options = self.options;
 
 		if self.l_id.is_some() { self.widgets.app_button.disconnect(
 			glib::signal::SignalHandlerId::from_glib(self.l_id.as_ref().unwrap().to_glib())) }
 		self.l_id = Some(self.widgets.app_button.connect_clicked(move |trigger| {
-			SourceMeter::show_popup(&trigger, &pulse, index);
+			SourceMeter::show_popup(&trigger, &pulse, index, options);
 		}));
 	}
 
 
 	/**
-	 * Updates each scale widget to reflect the current volume level.
+	 * Updates each scale widget and the status button to reflect the current volume level.
 	 */
 
 	fn update_widgets(&mut self) {
-		for (i, v) in self.data.volume.get().iter().enumerate() {
+		let volumes = if self.split { self.data.volume.get().to_vec() } else { vec![ self.data.volume.max() ] };
+		for (i, v) in volumes.iter().enumerate() {
 			if let Some(scale) = self.widgets.scales_inner.get_children().get(i) {
 				let scale = scale.clone().downcast::<gtk::Scale>().expect("Scales box has non-scale children.");
 				scale.set_sensitive(!self.data.muted);
-				scale.set_value(v.0 as f64);
+				scale.set_value(Meter::scale_value(self.options, *v));
+				scale.set_tooltip_text(Some(&Meter::scale_tooltip(&self.data, if self.split { Some(i) } else { None })));
 			}
 		}
+
+		Meter::update_balance(&self.widgets.balance_inner, &self.data);
+
+		let status_vol = if self.data.muted { 0 } else { self.data.volume.max().0 };
+
+		self.widgets.status_icon.set_from_icon_name(Some(INPUT_ICONS[
+			if status_vol == 0 { 0 } else if status_vol >= MAX_NATURAL_VOL { 3 }
+			else if status_vol >= MAX_NATURAL_VOL / 2 { 2 } else { 1 }]), gtk::IconSize::Button);
+
+		let status_text = Meter::status_label(self.options, Volume(status_vol));
+		self.widgets.status.set_label(&status_text);
+		self.widgets.status.set_tooltip_text(Some(&Meter::volume_tooltip(&self.data)));
+
+		if status_vol == 0 {self.widgets.status.get_style_context().add_class("muted") }
+		else { self.widgets.status.get_style_context().remove_class("muted") }
+	}
+
+
+	/**
+	 * Sets whether the source is being played through a sink, which is indicated with a badge.
+	 *
+	 * * `loopback` - Whether the source is being played through a sink.
+	 */
+
+	pub fn set_loopback(&mut self, loopback: bool) {
+		if self.loopback == loopback { return; }
+		self.loopback = loopback;
+		self.update_badge();
+	}
+
+
+	/**
+	 * Shows a badge if the source is being listened to, or if it is suspended.
+	 * Listening takes precedence, as a source can't be suspended while it is being listened to.
+	 */
+
+	fn update_badge(&self) {
+		Meter::set_badge(&self.widgets,
+			if self.loopback { Some(( "audio-headphones-symbolic", "Listening to this device" )) }
+			else if self.data.state == Some(DeviceState::Suspended) { Some(( "media-playback-pause-symbolic", "Suspended" )) }
+			else { None });
+	}
+
+
+	/**
+	 * Adds items to the popup menu to play the source through a sink.
+	 * The playback device and latency are chosen in a submenu, and choosing them while listening restarts the loopback.
+	 *
+	 * * `menu`      - The popup's menu box to add the items to.
+	 * * `root`      - The popup itself, which the submenu is added to.
+	 * * `pulse_shr` - The pulse store to bind events to.
+	 * * `index`     - The index of the source.
+	 */
+
+	fn add_listen_items(menu: &gtk::Box, root: &gtk::PopoverMenu, pulse_shr: &Shared<dyn Backend>, index: u32) {
+		let pulse = pulse_shr.borrow();
+		let store = pulse.store();
+		if store.sinks.is_empty() { return; }
+
+		let loopback = store.loopbacks.get(&index).copied();
+		let sink = loopback.map_or(store.default_sink, |l| l.sink);
+		let latency = loopback.map_or(DEFAULT_LOOPBACK_LATENCY, |l| l.latency);
+
+		menu.pack_start(&gtk::SeparatorMenuItem::new(), false, false, 3);
+
+		let listen = gtk::ModelButton::new();
+		listen.set_property_role(gtk::ButtonRole::Check);
+		listen.set_property_text(Some("Listen to This Device"));
+		listen.set_property_active(loopback.is_some());
+		let pulse_clone = pulse_shr.clone();
+		listen.connect_clicked(move |_| {
+			let pulse = pulse_clone.borrow();
+			if loopback.is_some() { pulse.stop_loopback(index); }
+			else { pulse.start_loopback(index, sink, latency); }
+		});
+		menu.add(&listen);
+
+		let options = gtk::ModelButton::new();
+		options.set_property_text(Some("Listen Options"));
+		options.set_property_menu_name(Some("listen"));
+		menu.add(&options);
+
+		let listen_menu = gtk::Box::new(gtk::Orientation::Vertical, 0);
+		root.add(&listen_menu);
+		root.set_child_submenu(&listen_menu, Some("listen"));
+
+		let back = gtk::ModelButton::new();
+		back.set_property_text(Some("Listen Options"));
+		back.set_property_menu_name(Some("main"));
+		back.set_property_inverted(true);
+		back.set_property_centered(true);
+		listen_menu.add(&back);
+
+		listen_menu.pack_start(&gtk::SeparatorMenuItem::new(), false, false, 3);
+
+		let label = gtk::Label::new(Some("Playback Device"));
+		label.set_sensitive(false);
+		listen_menu.pack_start(&label, true, true, 3);
+
+		let mut sinks: Vec<( u32, String )> = store.sinks.iter().map(|(i, s)| ( *i, s.data.description.clone() )).collect();
+		sinks.sort_by(|a, b| a.1.cmp(&b.1));
+
+		for ( i, description ) in sinks {
+			let button = gtk::ModelButton::new();
+			button.set_property_role(gtk::ButtonRole::Radio);
+			button.set_property_active(i == sink);
+			let button_label = gtk::Label::new(Some(&description));
+			button_label.set_ellipsize(pango::EllipsizeMode::End);
+			button_label.set_max_width_chars(18);
+			button.get_child().unwrap().downcast::<gtk::Box>().unwrap().add(&button_label);
+
+			let root = root.clone();
+			let pulse_clone = pulse_shr.clone();
+			button.connect_clicked(move |_| {
+				pulse_clone.borrow().start_loopback(index, i, latency);
+				root.popdown();
+			});
+			listen_menu.add(&button);
+		}
+
+		listen_menu.pack_start(&gtk::SeparatorMenuItem::new(), false, false, 3);
+
+		let label = gtk::Label::new(Some("Latency"));
+		label.set_sensitive(false);
+		listen_menu.pack_start(&label, true, true, 3);
+
+		for choice in &LOOPBACK_LATENCIES {
+			let choice = *choice;
+			let button = gtk::ModelButton::new();
+			button.set_property_role(gtk::ButtonRole::Radio);
+			button.set_property_active(choice == latency);
+			button.set_property_text(Some(&format!("{} ms", choice)));
+
+			let root = root.clone();
+			let pulse_clone = pulse_shr.clone();
+			button.connect_clicked(move |_| {
+				pulse_clone.borrow().start_loopback(index, sink, choice);
+				root.popdown();
+			});
+			listen_menu.add(&button);
+		}
 	}
 
 
 	/**
 	 * Shows a popup menu on the top button, with items to set
-	 * the Sink as default, and change the visible source.
+	 * the Sink as default, suspend it, change its port and maximum volume, listen to it, and change the visible source.
 	 */
 
-	fn show_popup(trigger: &gtk::Button, pulse_shr: &Shared<Pulse>, index: u32) {
+	fn show_popup(trigger: &gtk::Button, pulse_shr: &Shared<dyn Backend>, index: u32, options: ScaleOptions) {
 		let pulse = pulse_shr.borrow_mut();
 		let root = gtk::PopoverMenu::new();
 		root.set_border_width(6);
@@ -122,8 +280,8 @@ impl SourceMeter {
 		let set_default = gtk::ModelButton::new();
 		set_default.set_property_role(gtk::ButtonRole::Check);
 		set_default.set_property_text(Some("Set as Default"));
-		set_default.set_property_active(pulse.default_source == index);
-		set_default.set_sensitive(pulse.default_source != index);
+		set_default.set_property_active(pulse.store().default_source == index);
+		set_default.set_sensitive(pulse.store().default_source != index);
 			
 		let pulse_clone = pulse_shr.clone();
 		set_default.connect_clicked(move |set_default| {
@@ -133,18 +291,29 @@ impl SourceMeter {
 		});
 		menu.add(&set_default);
 
-		if pulse.sources.len() >= 2 {
+		if let Some(device) = pulse.store().sources.get(&index) {
+			Meter::add_suspend_item(&menu, pulse_shr, &device.data);
+			Meter::add_port_items(&menu, &root, pulse_shr, &device.data);
+			Meter::add_max_volume_items(&menu, &root, trigger, &device.data, options);
+			SourceMeter::add_listen_items(&menu, &root, pulse_shr, index);
+		}
+
+		if pulse.store().sources.len() >= 2 {
 			menu.pack_start(&gtk::SeparatorMenuItem::new(), false, false, 3);
 
 			let label = gtk::Label::new(Some("Visible Input"));
 			label.set_sensitive(false);
 			menu.pack_start(&label, true, true, 3);
 			
-			for (i, v) in &pulse.sources {
+			for (i, v) in &pulse.store().sources {
 				let button = gtk::ModelButton::new();
 				button.set_property_role(gtk::ButtonRole::Radio);
 				button.set_property_active(v.data.index == index);
-				let button_label = gtk::Label::new(Some(&v.data.description));
+				let description = match v.monitor_of.and_then(|sink| pulse.store().sinks.get(&sink)) {
+					Some(sink) => format!("Monitor of {}", sink.data.description),
+					None => v.data.description.clone()
+				};
+				let button_label = gtk::Label::new(Some(&description));
 				button_label.set_ellipsize(pango::EllipsizeMode::End);
 				button_label.set_max_width_chars(18);
 				button.get_child().unwrap().downcast::<gtk::Box>().unwrap().add(&button_label);
@@ -178,13 +347,21 @@ impl Meter for SourceMeter {
 		self.rebuild_widgets();
 	}
 
+	fn set_scale_options(&mut self, options: ScaleOptions) {
+		if self.options == options { return }
+		self.options = options;
+		self.rebuild_widgets();
+	}
+
 	fn set_data(&mut self, data: &MeterData) {
 		let volume_old = self.data.volume;
 		let volume_changed = data.volume != volume_old;
 
-		if data.t != self.data.t || data.index != self.data.index || data.volume.len() != self.data.volume.len() {
+		if data.t != self.data.t || data.index != self.data.index || data.volume.len() != self.data.volume.len()
+			|| data.channel_map != self.data.channel_map {
 			self.data.t = data.t;
 			self.data.volume = data.volume;
+			self.data.channel_map = data.channel_map;
 			self.data.index = data.index;
 			self.rebuild_widgets();
 		}
@@ -202,42 +379,31 @@ impl Meter for SourceMeter {
 		if data.description != self.data.description {
 			self.data.description = data.description.clone();
 			self.widgets.label.set_label(&self.data.description);
-			self.widgets.app_button.set_tooltip_text(Some(&self.data.description));
+			self.widgets.app_button.set_tooltip_text(Some(&Meter::device_tooltip(&self.data)));
+		}
+
+		if data.state != self.data.state {
+			self.data.state = data.state;
+			self.widgets.app_button.set_tooltip_text(Some(&Meter::device_tooltip(&self.data)));
+			self.update_badge();
 		}
 
 		if volume_changed || data.muted != self.data.muted {
 			self.data.volume = data.volume;
 			self.data.muted = data.muted;
 			self.update_widgets();
-
-			let status_vol = if self.data.muted { 0 } else { self.data.volume.max().0 };
-
-			self.widgets.status_icon.set_from_icon_name(Some(INPUT_ICONS[
-				if status_vol == 0 { 0 } else if status_vol >= MAX_NATURAL_VOL { 3 }
-				else if status_vol >= MAX_NATURAL_VOL / 2 { 2 } else { 1 }]), gtk::IconSize::Button);
-
-			let mut vol_scaled = ((status_vol as f64) / MAX_NATURAL_VOL as f64 * 100.0).round() as u8;
-			if vol_scaled > 150 { vol_scaled = 150 }
-
-			let mut string = vol_scaled.to_string();
-			string.push('%');
-
-			self.widgets.status.set_label(&string);
-
-			if status_vol == 0 {self.widgets.status.get_style_context().add_class("muted") }
-			else { self.widgets.status.get_style_context().remove_class("muted") }
 		}
 	}
 
-	fn set_peak(&mut self, peak: Option<u32>) {
+	fn set_peak(&mut self, peak: Option<Vec<u32>>) {
 		if self.peak != peak {
 			self.peak = peak;
 
-			if self.peak.is_some() {
-				for (i, s) in self.widgets.scales_inner.get_children().iter().enumerate() {
+			if let Some(peaks) = &self.peak {
+				let fills = Meter::peak_fills(&self.data, peaks, self.split, self.options);
+				for (s, peak_scaled) in self.widgets.scales_inner.get_children().iter().zip(fills) {
 					let s = s.clone().downcast::<gtk::Scale>().expect("Scales box has non-scale children.");
-					let peak_scaled = self.peak.unwrap() as f64 * (self.data.volume.get()[i].0 as f64 / MAX_SCALE_VOL as f64);
-					s.set_fill_level(peak_scaled as f64);
+					s.set_fill_level(peak_scaled);
 					s.set_show_fill_level(!self.data.muted && peak_scaled > 0.5);
 					s.get_style_context().add_class("visualizer");
 				}
@@ -3,12 +3,14 @@
  */
 
 use gtk::prelude::*;
+use libpulse::volume::Volume;
 use glib::translate::{ ToGlib, FromGlib };
 
 use crate::shared::Shared;
-use crate::pulse::{ Pulse, StreamType };
-use super::base_meter::{ Meter, MeterWidgets, MeterData };
-use super::base_meter::{ MAX_NATURAL_VOL, MAX_SCALE_VOL, INPUT_ICONS, OUTPUT_ICONS };
+use crate::backend::{ Backend, StreamType, StreamData };
+use crate::hidden::Filter;
+use super::base_meter::{ Meter, MeterWidgets, MeterData, ScaleOptions };
+use super::base_meter::{ MAX_NATURAL_VOL, INPUT_ICONS, OUTPUT_ICONS };
 
 
 /**
@@ -21,12 +23,15 @@ pub struct StreamMeter {
 
 	data: MeterData,
 	widgets: MeterWidgets,
-	pulse: Shared<Pulse>,
+	pulse: Shared<dyn Backend>,
 
 	pub split: bool,
-	pub peak: Option<u32>,
+	options: ScaleOptions,
+	pub peak: Option<Vec<u32>>,
 
 	b_id: Option<glib::signal::SignalHandlerId>,
+	l_id: Option<glib::signal::SignalHandlerId>,
+	d_id: Option<glib::signal::SignalHandlerId>,
 }
 
 impl StreamMeter {
@@ -35,7 +40,7 @@ impl StreamMeter {
 	 * Creates a new StreamMeter.
 	 */
 
-	pub fn new(pulse: Shared<Pulse>) -> Self {
+	pub fn new(pulse: Shared<dyn Backend>) -> Self {
 		let widgets = Meter::build_meter();
 		Self {
 			widget: widgets.root.clone(),
@@ -44,7 +49,7 @@ impl StreamMeter {
 			widgets,
 			data: MeterData::default(),
 
-			split: false, peak: None, b_id: None
+			split: false, options: ScaleOptions::default(), peak: None, b_id: None, l_id: None, d_id: None
 		}
 	}
 
@@ -55,10 +60,15 @@ impl StreamMeter {
 	 */
 
 	fn rebuild_widgets(&mut self) {
-		let scales = Meter::build_scales(&self.pulse, &self.data, self.split);
+		let scales = Meter::build_scales(&self.pulse, &self.data, self.split, self.options);
 		self.widgets.scales_outer.remove(&self.widgets.scales_inner);
 		self.widgets.scales_outer.pack_start(&scales, true, false, 0);
 		self.widgets.scales_inner = scales;
+
+		let balance = Meter::build_balance(&self.pulse, &self.data, self.split);
+		self.widgets.balance_outer.remove(&self.widgets.balance_inner);
+		self.widgets.balance_outer.pack_start(&balance, true, true, 0);
+		self.widgets.balance_inner = balance;
 		self.update_widgets();
 
 		let t 		= self.data.t;
@@ -69,20 +79,157 @@ impl StreamMeter {
 		self.b_id = Some(self.widgets.status.connect_clicked(move |status| {
 			pulse.borrow_mut().set_muted(t, index, !status.get_style_context().has_class("muted"));
 		}));
+
+		let pulse = self.pulse.clone();
+
+		// The app button opens the popup too, as the device button is hidden when the device isn't listed, e.g. monitors.
+		if self.l_id.is_some() { self.widgets.app_button.disconnect(
+			glib::signal::SignalHandlerId::from_glib(self.l_id.as_ref().unwrap().to_glib())) }
+		self.l_id = Some(self.widgets.app_button.connect_clicked(move |trigger| {
+			StreamMeter::show_popup(&trigger, &pulse, t, index);
+		}));
+
+		let pulse = self.pulse.clone();
+
+		if self.d_id.is_some() { self.widgets.select.disconnect(
+			glib::signal::SignalHandlerId::from_glib(self.d_id.as_ref().unwrap().to_glib())) }
+		self.d_id = Some(self.widgets.select.connect_clicked(move |trigger| {
+			StreamMeter::show_popup(&trigger, &pulse, t, index);
+		}));
 	}
 
 
 	/**
-	 * Updates each scale widget to reflect the current volume level.
+	 * Sets the description of the device the stream is connected to,
+	 * which is displayed below the app name. Hides the device button if None.
+	 *
+	 * * `device` - The device's description.
+	 */
+
+	pub fn set_device(&mut self, device: Option<&str>) {
+		match device {
+			Some(device) => {
+				if self.widgets.select_label.get_text() != device {
+					self.widgets.select_label.set_label(device);
+					self.widgets.select.set_tooltip_text(Some(device));
+				}
+				self.widgets.select.show();
+			},
+			None => self.widgets.select.hide()
+		}
+	}
+
+
+	/**
+	 * Updates each scale widget and the status button to reflect the current volume level.
 	 */
 
 	fn update_widgets(&mut self) {
-		for (i, v) in self.data.volume.get().iter().enumerate() {
+		let volumes = if self.split { self.data.volume.get().to_vec() } else { vec![ self.data.volume.max() ] };
+		for (i, v) in volumes.iter().enumerate() {
 			if let Some(scale) = self.widgets.scales_inner.get_children().get(i) {
 				let scale = scale.clone().downcast::<gtk::Scale>().expect("Scales box has non-scale children.");
-				scale.set_value(if self.data.muted { 0.0 } else { v.0 as f64 });
+				scale.set_value(if self.data.muted { 0.0 } else { Meter::scale_value(self.options, *v) });
+				scale.set_tooltip_text(Some(&Meter::scale_tooltip(&self.data, if self.split { Some(i) } else { None })));
 			}
 		}
+
+		Meter::update_balance(&self.widgets.balance_inner, &self.data);
+
+		let status_vol = if self.data.muted { 0 } else { self.data.volume.max().0 };
+
+		let &icons = if self.data.t == StreamType::Sink || self.data.t == StreamType::SinkInput
+			{ &OUTPUT_ICONS } else { &INPUT_ICONS };
+
+		self.widgets.status_icon.set_from_icon_name(Some(icons[
+			if status_vol == 0 { 0 } else if status_vol >= MAX_NATURAL_VOL { 3 }
+			else if status_vol >= MAX_NATURAL_VOL / 2 { 2 } else { 1 }]), gtk::IconSize::Button);
+
+		let status_text = Meter::status_label(self.options, Volume(status_vol));
+		self.widgets.status.set_label(&status_text);
+		self.widgets.status.set_tooltip_text(Some(&Meter::volume_tooltip(&self.data)));
+
+		if status_vol == 0 {self.widgets.status.get_style_context().add_class("muted") }
+		else { self.widgets.status.get_style_context().remove_class("muted") }
+	}
+
+
+	/**
+	 * Shows a popup menu on the top button, with items to move
+	 * the stream to a different output or input device, hide it, and disconnect the stream or its client.
+	 */
+
+	fn show_popup(trigger: &gtk::Button, pulse_shr: &Shared<dyn Backend>, t: StreamType, index: u32) {
+		let pulse = pulse_shr.borrow_mut();
+		let ( current, client, filter ) = match pulse.store().get_stream(t, index) {
+			Some(stream) => ( stream.monitor_index, stream.client, Filter::for_stream(t, &stream.data.name, &stream.app) ),
+			None => return
+		};
+
+		let root = gtk::PopoverMenu::new();
+		root.set_border_width(6);
+
+		let menu = gtk::Box::new(gtk::Orientation::Vertical, 0);
+		menu.set_size_request(132, -1);
+		root.add(&menu);
+
+		let ( title, devices ) = if t == StreamType::SinkInput { ( "Output Device", &pulse.store().sinks ) }
+			else { ( "Input Device", &pulse.store().sources ) };
+
+		// The devices are sorted, so that their order doesn't change every time the popup is opened.
+		let mut devices: Vec<( &u32, &StreamData )> = devices.iter().collect();
+		devices.sort_by(|( _, a ), ( _, b )| a.data.description.cmp(&b.data.description));
+
+		let label = gtk::Label::new(Some(title));
+		label.set_sensitive(false);
+		menu.pack_start(&label, true, true, 3);
+
+		for (i, v) in devices {
+			let button = gtk::ModelButton::new();
+			button.set_property_role(gtk::ButtonRole::Radio);
+			button.set_property_active(*i == current);
+			let button_label = gtk::Label::new(Some(&v.data.description));
+			button_label.set_ellipsize(pango::EllipsizeMode::End);
+			button_label.set_max_width_chars(18);
+			button.get_child().unwrap().downcast::<gtk::Box>().unwrap().add(&button_label);
+
+			let i = *i;
+			let root = root.clone();
+			let pulse_clone = pulse_shr.clone();
+			button.connect_clicked(move |_| {
+				pulse_clone.borrow_mut().move_stream(t, index, i);
+				root.popdown();
+			});
+
+			menu.add(&button);
+		}
+
+		menu.pack_start(&gtk::SeparatorMenuItem::new(), false, false, 3);
+
+		let hide = gtk::ModelButton::new();
+		hide.set_property_text(Some("Hide This Stream"));
+		let pulse_clone = pulse_shr.clone();
+		hide.connect_clicked(move |_| pulse_clone.borrow_mut().store_mut().hide_list.add(filter.clone()));
+		menu.add(&hide);
+
+		let kill_stream = gtk::ModelButton::new();
+		kill_stream.set_property_text(Some("Disconnect Stream"));
+		let pulse_clone = pulse_shr.clone();
+		kill_stream.connect_clicked(move |_| pulse_clone.borrow().kill_stream(t, index));
+		menu.add(&kill_stream);
+
+		// Disconnecting the client disconnects all of its streams, and usually closes the application's connection to the server.
+		if let Some(client) = client {
+			let kill_client = gtk::ModelButton::new();
+			kill_client.set_property_text(Some("Disconnect Client"));
+			let pulse_clone = pulse_shr.clone();
+			kill_client.connect_clicked(move |_| pulse_clone.borrow().kill_client(client));
+			menu.add(&kill_client);
+		}
+
+		for child in &root.get_children() { child.show_all(); }
+		root.set_relative_to(Some(trigger));
+		root.popup();
 	}
 }
 
@@ -97,13 +244,21 @@ impl Meter for StreamMeter {
 		self.rebuild_widgets();
 	}
 
+	fn set_scale_options(&mut self, options: ScaleOptions) {
+		if self.options == options { return }
+		self.options = options;
+		self.rebuild_widgets();
+	}
+
 	fn set_data(&mut self, data: &MeterData) {
 		let volume_old = self.data.volume;
 		let volume_changed = data.volume != volume_old;
 
-		if data.t != self.data.t || data.index != self.data.index || data.volume.len() != self.data.volume.len() {
+		if data.t != self.data.t || data.index != self.data.index || data.volume.len() != self.data.volume.len()
+			|| data.channel_map != self.data.channel_map {
 			self.data.t = data.t;
 			self.data.volume = data.volume;
+			self.data.channel_map = data.channel_map;
 			self.data.index = data.index;
 			self.rebuild_widgets();
 		}
@@ -127,37 +282,17 @@ impl Meter for StreamMeter {
 			self.data.volume = data.volume;
 			self.data.muted = data.muted;
 			self.update_widgets();
-
-			let status_vol = if self.data.muted { 0 } else { self.data.volume.max().0 };
-
-			let &icons = if self.data.t == StreamType::Sink || self.data.t == StreamType::SinkInput
-				{ &OUTPUT_ICONS } else { &INPUT_ICONS };
-
-			self.widgets.status_icon.set_from_icon_name(Some(icons[
-				if status_vol == 0 { 0 } else if status_vol >= MAX_NATURAL_VOL { 3 }
-				else if status_vol >= MAX_NATURAL_VOL / 2 { 2 } else { 1 }]), gtk::IconSize::Button);
-
-			let mut vol_scaled = ((status_vol as f64) / MAX_NATURAL_VOL as f64 * 100.0).round() as u8;
-			if vol_scaled > 150 { vol_scaled = 150 }
-
-			let mut string = vol_scaled.to_string();
-			string.push('%');
-
-			self.widgets.status.set_label(&string);
-
-			if status_vol == 0 {self.widgets.status.get_style_context().add_class("muted") }
-			else { self.widgets.status.get_style_context().remove_class("muted") }
 		}
 	}
 
-	fn set_peak(&mut self, peak: Option<u32>) {
+	fn set_peak(&mut self, peak: Option<Vec<u32>>) {
 		self.peak = peak;
 
-		if self.peak.is_some() {
-			for (i, s) in self.widgets.scales_inner.get_children().iter().enumerate() {
+		if let Some(peaks) = &self.peak {
+			let fills = Meter::peak_fills(&self.data, peaks, self.split, self.options);
+			for (s, peak_scaled) in self.widgets.scales_inner.get_children().iter().zip(fills) {
 				let s = s.clone().downcast::<gtk::Scale>().expect("Scales box has non-scale children.");
-				let peak_scaled = self.peak.unwrap() as f64 * (self.data.volume.get()[i].0 as f64 / MAX_SCALE_VOL as f64);
-				s.set_fill_level(peak_scaled as f64);
+				s.set_fill_level(peak_scaled);
 				s.set_show_fill_level(!self.data.muted && peak_scaled > 0.5);
 				s.get_style_context().add_class("visualizer");
 			}
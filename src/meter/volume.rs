@@ -0,0 +1,224 @@
+/*!
+ * Conversions between pulse volumes and slider positions, and helpers for formatting volumes.
+ */
+
+use libpulse::volume::{ Volume, VolumeDB, VolumeLinear };
+
+use super::base_meter::MAX_NATURAL_VOL;
+
+/** The quietest volume the decibel curve can represent, below this the slider is muted. */
+const MIN_DB: f64 = -60.0;
+
+
+/**
+ * Describes how a slider's position maps to a volume.
+ */
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum VolumeCurve {
+	/** The position is proportional to the signal's amplitude. */
+	Linear,
+
+	/** The position is proportional to pulse's volume, which is cubic in amplitude. This is what pavucontrol does. */
+	#[default]
+	Cubic,
+
+	/** The position is proportional to the volume in decibels, down to MIN_DB. */
+	Decibel
+}
+
+impl VolumeCurve {
+
+	/**
+	 * Gets the curve's name, as used in the config file and preference actions.
+	 */
+
+	pub fn name(self) -> &'static str {
+		match self {
+			VolumeCurve::Linear => "linear",
+			VolumeCurve::Cubic => "cubic",
+			VolumeCurve::Decibel => "decibel"
+		}
+	}
+
+
+	/**
+	 * Gets the curve with the name specified, as returned by `name`.
+	 *
+	 * * `name` - The name of the curve.
+	 */
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"linear" => Some(VolumeCurve::Linear),
+			"cubic" => Some(VolumeCurve::Cubic),
+			"decibel" => Some(VolumeCurve::Decibel),
+			_ => None
+		}
+	}
+
+
+	/**
+	 * Converts a volume to a slider position between 0 and 1.
+	 *
+	 * * `volume` - The volume to convert.
+	 * * `max`    - The volume at the top of the slider.
+	 */
+
+	pub fn to_position(self, volume: Volume, max: Volume) -> f64 {
+		if volume.is_muted() || max.is_muted() { return 0.0; }
+
+		let position = match self {
+			VolumeCurve::Linear => VolumeLinear::from(volume).0 / VolumeLinear::from(max).0,
+			VolumeCurve::Cubic => volume.0 as f64 / max.0 as f64,
+			VolumeCurve::Decibel => {
+				let max_db = VolumeDB::from(max).0;
+				(VolumeDB::from(volume).0 - MIN_DB) / (max_db - MIN_DB)
+			}
+		};
+
+		position.clamp(0.0, 1.0)
+	}
+
+
+	/**
+	 * Converts a slider position between 0 and 1 to a volume.
+	 *
+	 * * `position` - The position of the slider.
+	 * * `max`      - The volume at the top of the slider.
+	 */
+
+	pub fn to_volume(self, position: f64, max: Volume) -> Volume {
+		let position = position.clamp(0.0, 1.0);
+		if position <= 0.0 { return Volume::MUTED; }
+
+		match self {
+			VolumeCurve::Linear => Volume::from(VolumeLinear(VolumeLinear::from(max).0 * position)),
+			VolumeCurve::Cubic => Volume((max.0 as f64 * position).round() as u32),
+			VolumeCurve::Decibel => {
+				let max_db = VolumeDB::from(max).0;
+				Volume::from(VolumeDB(MIN_DB + (max_db - MIN_DB) * position))
+			}
+		}
+	}
+}
+
+
+/**
+ * Formats a volume as a percentage of the natural volume, e.g. "85%".
+ *
+ * * `volume` - The volume to format.
+ */
+
+pub fn format_percent(volume: Volume) -> String {
+	format!("{}%", (volume.0 as f64 / MAX_NATURAL_VOL as f64 * 100.0).round() as u32)
+}
+
+
+/**
+ * Formats a volume in decibels, e.g. "-4.2 dB".
+ *
+ * * `volume` - The volume to format.
+ */
+
+pub fn format_db(volume: Volume) -> String {
+	if volume.is_muted() { return "-∞ dB".to_owned(); }
+	let db = VolumeDB::from(volume).0;
+	// Avoid displaying "-0.0 dB" for volumes that are just under the natural volume.
+	if db.abs() < 0.05 { "0.0 dB".to_owned() } else { format!("{:.1} dB", db) }
+}
+
+
+#[cfg(test)]
+mod tests {
+	use libpulse::volume::{ Volume, VolumeDB };
+
+	use super::{ VolumeCurve, MIN_DB, format_percent, format_db };
+	use super::super::base_meter::{ MAX_NATURAL_VOL, MAX_SCALE_VOL };
+
+	const CURVES: [VolumeCurve; 3] = [ VolumeCurve::Linear, VolumeCurve::Cubic, VolumeCurve::Decibel ];
+
+
+	/**
+	 * Asserts that two volumes are within one step of each other, which is as close as the conversions can round.
+	 */
+
+	fn assert_near(actual: Volume, expected: Volume, curve: VolumeCurve) {
+		assert!((actual.0 as i64 - expected.0 as i64).abs() <= 1, "{:?}: expected {:?}, got {:?}", curve, expected, actual);
+	}
+
+
+	#[test]
+	fn muted_is_the_bottom_of_every_curve() {
+		let max = Volume(MAX_SCALE_VOL);
+		for curve in CURVES.iter().copied() {
+			assert_eq!(curve.to_position(Volume::MUTED, max), 0.0);
+			assert_eq!(curve.to_volume(0.0, max), Volume::MUTED);
+			assert_eq!(curve.to_volume(-1.0, max), Volume::MUTED);
+			assert_eq!(curve.to_position(Volume(MAX_NATURAL_VOL), Volume::MUTED), 0.0);
+		}
+	}
+
+
+	#[test]
+	fn max_is_the_top_of_every_curve() {
+		let max = Volume(MAX_SCALE_VOL);
+		for curve in CURVES.iter().copied() {
+			assert!((curve.to_position(max, max) - 1.0).abs() < 1e-9, "{:?}", curve);
+			assert_near(curve.to_volume(1.0, max), max, curve);
+			assert_near(curve.to_volume(2.0, max), max, curve);
+			assert_eq!(curve.to_position(Volume(MAX_SCALE_VOL * 2), max), 1.0);
+		}
+	}
+
+
+	#[test]
+	fn natural_volume_round_trips() {
+		let natural = Volume(MAX_NATURAL_VOL);
+		for max in [ Volume(MAX_NATURAL_VOL), Volume(MAX_SCALE_VOL), Volume(MAX_NATURAL_VOL * 2) ].iter().copied() {
+			for curve in CURVES.iter().copied() {
+				let position = curve.to_position(natural, max);
+				assert!(position > 0.0 && position <= 1.0, "{:?}", curve);
+				assert_near(curve.to_volume(position, max), natural, curve);
+			}
+		}
+	}
+
+
+	#[test]
+	fn cubic_is_proportional_to_the_volume() {
+		let max = Volume(MAX_SCALE_VOL);
+		assert!((VolumeCurve::Cubic.to_position(Volume(MAX_SCALE_VOL / 2), max) - 0.5).abs() < 1e-9);
+		assert_eq!(VolumeCurve::Cubic.to_volume(0.5, max), Volume(MAX_SCALE_VOL / 2));
+	}
+
+
+	#[test]
+	fn decibel_curve_is_floored_at_min_db() {
+		let max = Volume(MAX_SCALE_VOL);
+		let floor = Volume::from(VolumeDB(MIN_DB));
+		let below = Volume::from(VolumeDB(MIN_DB - 20.0));
+
+		assert!(VolumeCurve::Decibel.to_position(floor, max) < 1e-3);
+		assert_eq!(VolumeCurve::Decibel.to_position(below, max), 0.0);
+		assert!(VolumeCurve::Decibel.to_volume(1e-6, max).0 >= floor.0);
+		assert!(VolumeCurve::Linear.to_position(below, max) > 0.0);
+	}
+
+
+	#[test]
+	fn names_round_trip() {
+		for curve in CURVES.iter().copied() { assert_eq!(VolumeCurve::from_name(curve.name()), Some(curve)); }
+		assert_eq!(VolumeCurve::from_name("exponential"), None);
+		assert_eq!(VolumeCurve::default(), VolumeCurve::Cubic);
+	}
+
+
+	#[test]
+	fn volumes_are_formatted() {
+		assert_eq!(format_percent(Volume(MAX_NATURAL_VOL)), "100%");
+		assert_eq!(format_percent(Volume(MAX_SCALE_VOL)), "150%");
+		assert_eq!(format_db(Volume(MAX_NATURAL_VOL)), "0.0 dB");
+		assert_eq!(format_db(Volume::MUTED), "-∞ dB");
+	}
+}
@@ -5,38 +5,58 @@
 
 use slice_as_array::{ slice_as_array, slice_as_array_transmute };
 
-use libpulse::def::BufferAttr;
+use libpulse::def::{ BufferAttr, PortAvailable, SinkState, SourceState };
 use libpulse::callbacks::ListResult;
 use libpulse::sample::{ Spec, Format };
 use libpulse::mainloop::threaded::Mainloop;
 use libpulse::proplist::{ Proplist, properties };
 use libpulse::volume::{ Volume, ChannelVolumes };
-use libpulse::stream::{ Stream, FlagSet as StreamFlagSet, PeekResult };
+use libpulse::channelmap::Map as ChannelMap;
+use libpulse::stream::{ Stream, FlagSet as StreamFlagSet, PeekResult, SeekMode };
 use libpulse::context::subscribe::{ InterestMaskSet, Facility, Operation };
+use libpulse::operation::{ Operation as PulseOperation, State as OperationState };
 use libpulse::context::{ Context, FlagSet as CtxFlagSet, State as ContextState };
-use libpulse::context::introspect::{ ServerInfo, SourceInfo, SinkInfo, SinkInputInfo, SourceOutputInfo, CardInfo };
+use libpulse::context::introspect::{ ServerInfo, SourceInfo, SinkInfo, SinkInputInfo, SourceOutputInfo, CardInfo, ModuleInfo };
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{ HashMap, HashSet };
+use std::time::{ Duration, Instant };
 use std::sync::mpsc::{ channel, Sender, Receiver };
 
 use super::shared::Shared;
 use super::card::CardData;
-use super::meter::MeterData;
-use super::meter::MAX_NATURAL_VOL;
+use super::meter::{ MeterData, PortData };
+use super::meter::{ MAX_NATURAL_VOL, format_percent };
+use super::backend::{ Backend, Store, StreamType, StreamData, ModuleData, LoopbackData, ServerData, ConnectionState, DeviceState, MONITOR_SOURCE_SUFFIX };
+use super::backend::{ APP_NAME, PEAK_STREAM_NAME, TEST_TONE_STREAM_NAME, is_own_stream };
+use super::rules::{ Rules, AppProps };
+use super::hidden::HideList;
+use super::events::EventKind;
+use super::ducking::Ducking;
 
+/** The delay before the first reconnection attempt after the connection is lost. */
+const RECONNECT_DELAY_MIN: Duration = Duration::from_millis(500);
 
-/**
- * Represents a stream's underlying type.
- */
+/** The maximum delay between reconnection attempts. */
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(10);
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub enum StreamType {
-	Sink, SinkInput, Source, SourceOutput
-}
+/** The sample rate of the speaker test tone. */
+const TEST_TONE_RATE: u32 = 48000;
 
-impl Default for StreamType {
-	fn default() -> Self { StreamType::Sink }
-}
+/** The frequency of the speaker test tone, in hertz. */
+const TEST_TONE_FREQUENCY: f32 = 440.0;
+
+/** The length of the speaker test tone. */
+const TEST_TONE_DURATION: Duration = Duration::from_millis(600);
+
+/** The time it takes ducking to move a stream's volume between its full and ducked levels. */
+const DUCKING_RAMP: Duration = Duration::from_millis(800);
+
+/** The time between the steps that ducking moves volumes in. */
+const DUCKING_STEP: Duration = Duration::from_millis(200);
+
+/** The longest time to wait for the server to apply changes before disconnecting. */
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(1);
 
 
 /**
@@ -45,13 +65,19 @@ impl Default for StreamType {
  * current state of the pulse server.
  */
 
+#[allow(clippy::large_enum_variant)]
 enum TxMessage {
-	Default(String, String),
+	Connection(ConnectionState),
+	Server(ServerData),
 	StreamUpdate(StreamType, TxStreamData),
 	StreamRemove(StreamType, u32),
 	CardUpdate(CardData),
 	CardRemove(u32),
-	Peak(StreamType, u32, u32)
+	ModuleUpdate(ModuleData),
+	ModuleRemove(u32),
+	Loopback(u32, LoopbackData),
+	Error(String),
+	Peak(StreamType, u32, Vec<u32>)
 }
 
 
@@ -63,22 +89,40 @@ enum TxMessage {
 pub struct TxStreamData {
 	pub data: MeterData,
 	pub monitor_index: u32,
+
+	/** The application properties of the stream, empty for devices. */
+	pub app: AppProps,
+
+	/** The index of the module that created the stream, if it was created by one. */
+	pub owner_module: Option<u32>,
+
+	/** The index of the client that owns the stream, None for devices. */
+	pub client: Option<u32>,
+
+	/** The index of the sink that the source monitors, if it is a monitor source. */
+	pub monitor_of: Option<u32>,
+
+	/** Whether the stream was just created, rather than discovered or changed. */
+	pub new: bool,
 }
 
 
 /**
- * Stored representation of a pulse stream.
- * The stream index is not in this struct, but
- * it is the index it is keyed under in its hashmap.
+ * The state of a playback stream that has been lowered by ducking.
  */
 
-pub struct StreamData {
-	pub data: MeterData,
+struct DuckedStream {
+	/** The volume the stream had before it was ducked, which it is restored to. */
+	volume: ChannelVolumes,
 
-	pub peak: u32,
-	pub repetitions: u32,
-	pub monitor_index: u32,
-	pub monitor: Shared<Stream>
+	/** The volume that was last set on the stream, so that the server reporting it isn't mistaken for a change by the user. */
+	applied: ChannelVolumes,
+
+	/** Whether the server has reported the volume that was last set, after which any other volume is a change by the user. */
+	confirmed: bool,
+
+	/** Whether the user changed the stream's volume while it was ducked, in which case it is left alone. */
+	released: bool,
 }
 
 
@@ -87,9 +131,9 @@ struct Channel<T> { tx: Sender<T>, rx: Receiver<T> }
 
 
 /**
- * The main controller for all pulse server interactions.
+ * The libpulse implementation of the audio backend.
  * Handles peak monitoring, stream discovery, and meter information.
- * Stores data for all known streams, allowing public access.
+ * Stores data for all known streams in its Store.
  */
 
 pub struct Pulse {
@@ -97,16 +141,30 @@ pub struct Pulse {
 	context: Shared<Context>,
 	channel: Channel<TxMessage>,
 
-	pub default_sink: u32,
-	pub default_source: u32,
-	pub active_sink: u32,
-	pub active_source: u32,
+	/** The server to connect to, or None for the default server. */
+	server: Option<String>,
+
+	reconnect_at: Option<Instant>,
+	reconnect_delay: Duration,
+
+	monitors: HashMap<(StreamType, u32), Shared<Stream>>,
+	monitored: HashSet<(StreamType, u32)>,
+	test_tone: Option<Shared<Stream>>,
+
+	/** Whether monitor sources and the streams matched by the hide list are shown. */
+	show_monitors: bool,
+	show_hidden: bool,
 
-	pub sinks: HashMap<u32, StreamData>,
-	pub sink_inputs: HashMap<u32, StreamData>,
-	pub sources: HashMap<u32, StreamData>,
-	pub source_outputs: HashMap<u32, StreamData>,
-	pub cards: HashMap<u32, CardData>,
+	/** The streams that are hidden, kept so that they can be shown again, and the hide list version they were filtered with. */
+	hidden: HashMap<(StreamType, u32), StreamData>,
+	hidden_version: u32,
+
+	/** The playback streams lowered by ducking, the factor their volumes are multiplied by, and when it was last changed. */
+	ducked: HashMap<u32, DuckedStream>,
+	duck_level: f64,
+	duck_updated: Instant,
+
+	store: Store,
 }
 
 impl Pulse {
@@ -116,229 +174,103 @@ impl Pulse {
 	 */
 
 	pub fn new() -> Self {
-		let mut proplist = Proplist::new().unwrap();
-		proplist.set_str(properties::APPLICATION_NAME, "Myxer").unwrap();
-
 		let mainloop = Shared::new(Mainloop::new().expect("Failed to initialize pulse mainloop."));
+		mainloop.borrow_mut().start().expect("Failed to start pulse mainloop.");
 
-		let context = Shared::new(
-			Context::new_with_proplist(&*mainloop.borrow(), "Myxer Context", &proplist)
-			.expect("Failed to initialize pulse context."));
-
+		let context = Shared::new(Pulse::create_context(&mainloop.borrow()));
 		let ( tx, rx ) = channel::<TxMessage>();
 
 		Pulse {
 			mainloop, context,
 			channel: Channel { tx, rx },
-
-			default_sink: u32::MAX,
-			default_source: u32::MAX,
-			active_sink: u32::MAX,
-			active_source: u32::MAX,
-
-			sinks: HashMap::new(),
-			sink_inputs: HashMap::new(),
-			sources: HashMap::new(),
-			source_outputs: HashMap::new(),
-			cards: HashMap::new()
-		}
-	}
-
-
-	/**
-	 * Initiates a connection to pulse. Blocks until success, panics on failure.
-	 * TODO: Graceful error handling, with debug message.
-	 * TODO: Try to see if there's a way to avoid using unsafe? It's in the docs...  but...?
-	 */
-
-	pub fn connect(&mut self) {
-		let mut mainloop = self.mainloop.borrow_mut();
-		let mut ctx = self.context.borrow_mut();
-
-		let mainloop_shr_ref = self.mainloop.clone();
-		let ctx_shr_ref = self.context.clone();
-
-		ctx.set_state_callback(Some(Box::new(move || {
-			match unsafe { (*ctx_shr_ref.as_ptr()).get_state() } {
-				ContextState::Ready |
-				ContextState::Failed |
-				ContextState::Terminated =>
-					unsafe { (*mainloop_shr_ref.as_ptr()).signal(false); },
-				_ => {},
-			}
-		})));
-
-		ctx.connect(None, CtxFlagSet::NOFLAGS, None)
-			.expect("Failed to connect to the pulse server.");
-
-		mainloop.lock();
-		mainloop.start().expect("Failed to start pulse mainloop.");
-
-		loop {
-			match ctx.get_state() {
-				ContextState::Ready => {
-					ctx.set_state_callback(None);
-					mainloop.unlock();
-					break;
-				},
-				ContextState::Failed |
-				ContextState::Terminated => {
-					eprintln!("Context state failed/terminated, quitting...");
-					mainloop.unlock();
-					mainloop.stop();
-					panic!("Pulse session terminated.");
-				},
-				_ => { mainloop.wait(); },
-			}
-		}
-
-		drop(ctx);
-		drop(mainloop);
-		self.subscribe();
-	}
-
-
-	/**
-	 * Asynchronously sets the default sink to the index provided.
-	 * This is sometimes described as the fallback device.
-	 *
-	 * * `sink` - The sink index to set as the default.
-	 */
-
-	pub fn set_default_sink(&self, sink: u32) {
-		if let Some(sink) = self.sinks.get(&sink) {
-			let mut mainloop = self.mainloop.borrow_mut();
-			mainloop.lock();
-			self.context.borrow_mut().set_default_sink(&sink.data.name, |_|());
-			mainloop.unlock();
+			server: None,
+
+			reconnect_at: None,
+			reconnect_delay: RECONNECT_DELAY_MIN,
+
+			monitors: HashMap::new(),
+			monitored: HashSet::new(),
+			test_tone: None,
+			show_monitors: false,
+			show_hidden: false,
+			hidden: HashMap::new(),
+			hidden_version: 0,
+			ducked: HashMap::new(),
+			duck_level: 1.0,
+			duck_updated: Instant::now(),
+			store: Store { rules: Rules::load(), hide_list: HideList::load(), ducking: Ducking::load(), ..Store::new() }
 		}
 	}
 
 
 	/**
-	 * Asynchronously sets the default source to the index provided.
-	 * This is sometimes described as the fallback device.
-	 *
-	 * * `source` - The source index to set as the default.
+	 * Creates a new, unconnected pulse context on the mainloop provided.
 	 */
 
-	pub fn set_default_source(&self, source: u32) {
-		if let Some(source) = self.sources.get(&source) {
-			let mut mainloop = self.mainloop.borrow_mut();
-			mainloop.lock();
-			self.context.borrow_mut().set_default_source(&source.data.name, |_|());
-			mainloop.unlock();
-		}
-	}
-
-
-	/**
-	 * Sets the 'active' sink to the index provided.
-	 * This is the sink that is currently displayed on the interface.
-	 *
-	 * * `sink` - The sink index to set as active.
-	 */
+	fn create_context(mainloop: &Mainloop) -> Context {
+		let mut proplist = Proplist::new().unwrap();
+		proplist.set_str(properties::APPLICATION_NAME, APP_NAME).unwrap();
 
-	pub fn set_active_sink(&mut self, sink: u32) {
-		self.active_sink = sink;
+		Context::new_with_proplist(mainloop, "Myxer Context", &proplist)
+			.expect("Failed to initialize pulse context.")
 	}
 
 
 	/**
-	 * Sets the 'active' source to the index provided.
-	 * This is the source that is currently displayed on the interface.
-	 *
-	 * * `source` - The source to set as active.
+	 * Tears down the current context and connects a fresh one.
+	 * Called by update() once the reconnection delay has elapsed.
 	 */
 
-	pub fn set_active_source(&mut self, source: u32) {
-		self.active_source = source;
+	fn reconnect(&mut self) {
+		self.reconnect_at = None;
+		self.replace_context();
+		self.connect();
 	}
 
 
 	/**
-	 * Sets the volume of the stream to the volumes specified.
-	 * This operation is asynchronous, so changes will not be reflected immediately.
-	 *
-	 * * `t`       - The type of stream to set the volume of.
-	 * * `index`   - The index of the stream to set the volume of.
-	 * * `volumes` - The desired volumes to set the channels of the stream to.
+	 * Disconnects the current context, and replaces it with a fresh, unconnected one.
 	 */
 
-	pub fn set_volume(&self, t: StreamType, index: u32, volumes: ChannelVolumes) {
-		let mut introspect = self.context.borrow().introspect();
+	fn replace_context(&mut self) {
 		let mut mainloop = self.mainloop.borrow_mut();
 		mainloop.lock();
 
-		match t {
-			StreamType::Sink => { introspect.set_sink_volume_by_index(index, &volumes, None); },
-			StreamType::SinkInput => { introspect.set_sink_input_volume(index, &volumes, None); },
-			StreamType::Source => { introspect.set_source_volume_by_index(index, &volumes, None); },
-			StreamType::SourceOutput => { introspect.set_source_output_volume(index, &volumes, None); }
-		};
+		let context = Pulse::create_context(&mainloop);
+		let mut old = self.context.replace(context);
+		old.set_state_callback(None);
+		old.set_subscribe_callback(None);
+		old.disconnect();
 
 		mainloop.unlock();
 	}
 
 
 	/**
-	 * Mutes or unmutes a stream.
-	 * This operation is asynchronous, so changes will not be reflected immediately.
+	 * Updates the stored connection state.
+	 * Subscribes to the server once it is ready, and when the connection is lost,
+	 * clears the stores and schedules a reconnection with an increasing delay.
 	 *
-	 * * `t`     - The type of stream to update.
-	 * * `index` - The index of the stream to update.
-	 * * `mute`  - Whether the stream should be muted or not.
+	 * * `state` - The new connection state.
 	 */
 
-	pub fn set_muted(&self, t: StreamType, index: u32, mute: bool) {
-		// If unmuting a stream that has been set to 0 volume, it should be reset to full.
-		if !mute {
-			let entry = match t {
-				StreamType::Sink => self.sinks.get(&index),
-				StreamType::SinkInput => self.sink_inputs.get(&index),
-				StreamType::Source => self.sources.get(&index),
-				StreamType::SourceOutput => self.source_outputs.get(&index),
-			};
-
-			if let Some(entry) = entry {
-				if entry.data.volume.max().0 == 0 {
-					let mut volumes = ChannelVolumes::default();
-					volumes.set_len(entry.data.volume.len());
-					volumes.set(entry.data.volume.len(), Volume(MAX_NATURAL_VOL));
-					self.set_volume(t, index, volumes);
-				}
-			}
-		};
-
-		let mut introspect = self.context.borrow().introspect();
-		let mut mainloop = self.mainloop.borrow_mut();
-		mainloop.lock();
-
-		match t {
-			StreamType::Sink => { introspect.set_sink_mute_by_index(index, mute, None) },
-			StreamType::SinkInput => { introspect.set_sink_input_mute(index, mute, None) },
-			StreamType::Source => { introspect.set_source_mute_by_index(index, mute, None) },
-			StreamType::SourceOutput => { introspect.set_source_output_mute(index, mute, None) }
-		};
-
-		mainloop.unlock();
-	}
-
-
-	/**
-	 * Set's a sound card's profile.
-	 * This effects how the card behaves, and how the system can utilize it.
-	 *
-	 * * `index`   - The card index to update.
-	 * * `profile` - The profile name to update the card to.
-	 */
-	 
-	pub fn set_card_profile(&self, index: u32, profile: &str) {
-		let mut introspect = self.context.borrow().introspect();
-		let mut mainloop = self.mainloop.borrow_mut();
-		mainloop.lock();
-		introspect.set_card_profile_by_index(index, profile, None);
-		mainloop.unlock();
+	fn update_connection(&mut self, state: ConnectionState) {
+		match state {
+			ConnectionState::Ready => {
+				self.store.state = state;
+				self.reconnect_delay = RECONNECT_DELAY_MIN;
+				self.subscribe();
+			},
+			ConnectionState::Lost => {
+				if self.store.state == ConnectionState::Lost { return; }
+				self.store.state = state;
+				self.clear();
+
+				self.reconnect_at = Some(Instant::now() + self.reconnect_delay);
+				self.reconnect_delay = (self.reconnect_delay * 2).min(RECONNECT_DELAY_MAX);
+			},
+			ConnectionState::Connecting => self.store.state = state
+		}
 	}
 
 
@@ -351,10 +283,34 @@ impl Pulse {
 	fn subscribe(&mut self) {
 		/** Updates the client when the server information changes. */
 		fn tx_server(tx: &Sender<TxMessage>, item: &ServerInfo<'_>) {
-			tx.send(TxMessage::Default(item.default_sink_name.clone().unwrap().into_owned(),
-				item.default_source_name.clone().unwrap().into_owned())).unwrap();
+			tx.send(TxMessage::Server(ServerData {
+				server_name: item.server_name.clone().unwrap_or_default().into_owned(),
+				server_version: item.server_version.clone().unwrap_or_default().into_owned(),
+				host_name: item.host_name.clone().unwrap_or_default().into_owned(),
+				user_name: item.user_name.clone().unwrap_or_default().into_owned(),
+				default_sink: item.default_sink_name.clone().unwrap().into_owned(),
+				default_source: item.default_source_name.clone().unwrap().into_owned(),
+				sample_spec: item.sample_spec.print(),
+				channel_map: item.channel_map.print(),
+				cookie: item.cookie
+			})).unwrap();
 		};
 
+		/** Reads a sink or source port's data. */
+		fn tx_port(name: &Option<Cow<'_, str>>, description: &Option<Cow<'_, str>>, available: PortAvailable, priority: u32) -> PortData {
+			PortData {
+				name: name.clone().unwrap_or_default().into_owned(),
+				description: description.clone().unwrap_or_default().into_owned(),
+				available: available != PortAvailable::No,
+				priority
+			}
+		}
+
+		/** Reads the name of a sink or source port, which is empty if there is no port. */
+		fn tx_port_name(name: Option<&Option<Cow<'_, str>>>) -> String {
+			name.and_then(|name| name.clone()).unwrap_or_default().into_owned()
+		}
+
 		/** Updates the client when a sink changes. */
 		fn tx_sink(tx: &Sender<TxMessage>, result: ListResult<&SinkInfo<'_>>) {
 			if let ListResult::Item(item) = result {
@@ -366,15 +322,40 @@ impl Pulse {
 						name: item.name.clone().unwrap().into_owned(),
 						description: item.description.clone().unwrap().into_owned(),
 						volume: item.volume,
-						muted: item.mute
+						channel_map: item.channel_map,
+						muted: item.mute,
+						ports: item.ports.iter().map(|p| tx_port(&p.name, &p.description, p.available, p.priority)).collect(),
+						active_port: tx_port_name(item.active_port.as_ref().map(|p| &p.name)),
+						state: match item.state {
+							SinkState::Running => Some(DeviceState::Running),
+							SinkState::Idle => Some(DeviceState::Idle),
+							SinkState::Suspended => Some(DeviceState::Suspended),
+							SinkState::Invalid => None
+						}
 					},
-					monitor_index: item.monitor_source
+					monitor_index: item.monitor_source,
+					app: AppProps::default(),
+					owner_module: item.owner_module,
+					client: None,
+					monitor_of: None,
+					new: false
 				})).unwrap();
 			};
 		};
 
+		/** Reads the application properties of a stream from its proplist. */
+		fn tx_app_props(proplist: &Proplist) -> AppProps {
+			AppProps {
+				name: proplist.get_str("application.name").unwrap_or_default(),
+				binary: proplist.get_str("application.process.binary").unwrap_or_default(),
+				process_id: proplist.get_str("application.process.id").unwrap_or_default(),
+				process_host: proplist.get_str("application.process.host").unwrap_or_default(),
+				role: proplist.get_str("media.role").unwrap_or_default()
+			}
+		};
+
 		/** Updates the client when a sink input changes. */
-		fn tx_sink_input(tx: &Sender<TxMessage>, result: ListResult<&SinkInputInfo<'_>>) {
+		fn tx_sink_input(tx: &Sender<TxMessage>, result: ListResult<&SinkInputInfo<'_>>, new: bool) {
 			if let ListResult::Item(item) = result {
 				tx.send(TxMessage::StreamUpdate(StreamType::SinkInput, TxStreamData {
 					data: MeterData {
@@ -384,9 +365,16 @@ impl Pulse {
 						name: item.name.clone().unwrap().into_owned(),
 						description: item.proplist.get_str("application.name").unwrap_or_else(|| "".to_owned()),
 						volume: item.volume,
-						muted: item.mute
+						channel_map: item.channel_map,
+						muted: item.mute,
+						..Default::default()
 					},
-					monitor_index: item.sink
+					monitor_index: item.sink,
+					app: tx_app_props(&item.proplist),
+					owner_module: item.owner_module,
+					client: item.client,
+					monitor_of: None,
+					new
 				})).unwrap();
 			};
 		};
@@ -394,8 +382,6 @@ impl Pulse {
 		/** Updates the client when a source changes. */
 		fn tx_source(tx: &Sender<TxMessage>, result: ListResult<&SourceInfo<'_>>) {
 			if let ListResult::Item(item) = result {
-				let name = item.name.clone().unwrap().into_owned();
-				if name.ends_with(".monitor") { return; }
 				tx.send(TxMessage::StreamUpdate(StreamType::Source, TxStreamData {
 					data: MeterData {
 						t: StreamType::Source,
@@ -404,18 +390,30 @@ impl Pulse {
 						name: item.name.clone().unwrap().into_owned(),
 						description: item.description.clone().unwrap().into_owned(),
 						volume: item.volume,
-						muted: item.mute
+						channel_map: item.channel_map,
+						muted: item.mute,
+						ports: item.ports.iter().map(|p| tx_port(&p.name, &p.description, p.available, p.priority)).collect(),
+						active_port: tx_port_name(item.active_port.as_ref().map(|p| &p.name)),
+						state: match item.state {
+							SourceState::Running => Some(DeviceState::Running),
+							SourceState::Idle => Some(DeviceState::Idle),
+							SourceState::Suspended => Some(DeviceState::Suspended),
+							SourceState::Invalid => None
+						}
 					},
-					monitor_index: item.index
+					monitor_index: item.index,
+					app: AppProps::default(),
+					owner_module: item.owner_module,
+					client: None,
+					monitor_of: item.monitor_of_sink,
+					new: false
 				})).unwrap();
 			};
 		};
 
 		/** Updates the client when a source output changes. */
-		fn tx_source_output(tx: &Sender<TxMessage>, result: ListResult<&SourceOutputInfo<'_>>) {
+		fn tx_source_output(tx: &Sender<TxMessage>, result: ListResult<&SourceOutputInfo<'_>>, new: bool) {
 			if let ListResult::Item(item) = result {
-				let app_id = item.proplist.get_str("application.process.binary").unwrap_or_else(|| "".to_owned()).to_lowercase();
-				if app_id.contains("pavucontrol") || app_id.contains("myxer") { return; }
 				tx.send(TxMessage::StreamUpdate(StreamType::SourceOutput, TxStreamData {
 					data: MeterData {
 						t: StreamType::SourceOutput,
@@ -424,9 +422,16 @@ impl Pulse {
 						name: item.name.clone().unwrap().into_owned(),
 						description: item.proplist.get_str("application.name").unwrap_or_else(|| "".to_owned()),
 						volume: item.volume,
-						muted: item.mute
+						channel_map: item.channel_map,
+						muted: item.mute,
+						..Default::default()
 					},
-					monitor_index: item.source
+					monitor_index: item.source,
+					app: tx_app_props(&item.proplist),
+					owner_module: item.owner_module,
+					client: item.client,
+					monitor_of: None,
+					new
 				})).unwrap();
 			};
 		};
@@ -448,6 +453,18 @@ impl Pulse {
 			}
 		}
 
+		/** Updates the client when a module changes. */
+		fn tx_module(tx: &Sender<TxMessage>, result: ListResult<&ModuleInfo<'_>>) {
+			if let ListResult::Item(item) = result {
+				tx.send(TxMessage::ModuleUpdate(ModuleData {
+					index: item.index,
+					name: item.name.clone().unwrap_or_default().into_owned(),
+					argument: item.argument.clone().unwrap_or_default().into_owned(),
+					n_used: item.n_used
+				})).unwrap();
+			}
+		}
+
 		let mut mainloop = self.mainloop.borrow_mut();
 		mainloop.lock();
 		let mut context = self.context.borrow_mut();
@@ -456,19 +473,21 @@ impl Pulse {
 		let tx = self.channel.tx.clone();
 		introspect.get_sink_info_list(move |res| tx_sink(&tx, res));
 		let tx = self.channel.tx.clone();
-		introspect.get_sink_input_info_list(move |res| tx_sink_input(&tx, res));
+		introspect.get_sink_input_info_list(move |res| tx_sink_input(&tx, res, false));
 		let tx = self.channel.tx.clone();
 		introspect.get_source_info_list(move |res| tx_source(&tx, res));
 		let tx = self.channel.tx.clone();
-		introspect.get_source_output_info_list(move |res| tx_source_output(&tx, res));
+		introspect.get_source_output_info_list(move |res| tx_source_output(&tx, res, false));
 		let tx = self.channel.tx.clone();
 		introspect.get_card_info_list(move |res| tx_card(&tx, res));
 		let tx = self.channel.tx.clone();
+		introspect.get_module_info_list(move |res| tx_module(&tx, res));
+		let tx = self.channel.tx.clone();
 		introspect.get_server_info(move |res| tx_server(&tx, res));
 		
 		let tx = self.channel.tx.clone();
 		context.subscribe(InterestMaskSet::SERVER | InterestMaskSet::SINK | InterestMaskSet::SINK_INPUT |
-			InterestMaskSet::SOURCE | InterestMaskSet::SOURCE_OUTPUT | InterestMaskSet::CARD, |_|());
+			InterestMaskSet::SOURCE | InterestMaskSet::SOURCE_OUTPUT | InterestMaskSet::CARD | InterestMaskSet::MODULE, |_|());
 		context.set_subscribe_callback(Some(Box::new(move |fac, op, index| {
 			let tx = tx.clone();
 			let facility = fac.unwrap();
@@ -482,7 +501,10 @@ impl Pulse {
 				},
 				Facility::SinkInput => match operation {
 					Operation::Removed => tx.send(TxMessage::StreamRemove(StreamType::SinkInput, index)).unwrap(),
-					_ => { introspect.get_sink_input_info(index, move |res| tx_sink_input(&tx, res)); }
+					_ => {
+						let new = operation == Operation::New;
+						introspect.get_sink_input_info(index, move |res| tx_sink_input(&tx, res, new));
+					}
 				},
 				Facility::Source => match operation {
 					Operation::Removed => tx.send(TxMessage::StreamRemove(StreamType::Source, index)).unwrap(),
@@ -490,12 +512,19 @@ impl Pulse {
 				},
 				Facility::SourceOutput => match operation {
 					Operation::Removed => tx.send(TxMessage::StreamRemove(StreamType::SourceOutput, index)).unwrap(),
-					_ => { introspect.get_source_output_info(index, move |res| tx_source_output(&tx, res)); }
+					_ => {
+						let new = operation == Operation::New;
+						introspect.get_source_output_info(index, move |res| tx_source_output(&tx, res, new));
+					}
 				},
 				Facility::Card => match operation {
 					Operation::Removed => tx.send(TxMessage::CardRemove(index)).unwrap(),
 					_ => { introspect.get_card_info_by_index(index, move |res| tx_card(&tx, res)); }
 				},
+				Facility::Module => match operation {
+					Operation::Removed => tx.send(TxMessage::ModuleRemove(index)).unwrap(),
+					_ => { introspect.get_module_info(index, move |res| tx_module(&tx, res)); }
+				},
 				_ => ()
 			};
 		})));
@@ -505,206 +534,533 @@ impl Pulse {
 
 
 	/**
-	 * Handles queued messages from the pulse thread, updating the internal storage.
-	 * Returns a boolean indicating that a layout refresh is required.
+	 * Records a message in the event log, describing it relative to the current state of the store,
+	 * so it must be called before the message is applied. Peaks aren't recorded, as they arrive many times a second,
+	 * and neither are Myxer's own streams or stream updates without a change worth recording.
+	 *
+	 * * `message` - The message to record.
 	 */
 
-	pub fn update(&mut self) -> bool {
-		let mut received = false;
-
-		loop {
-			let res = self.channel.rx.try_recv();
-			match res {
-				Ok(res) => {
-					received = true;
-					match res {
-						TxMessage::Default(sink, source) => self.update_default(sink, source),
-						TxMessage::StreamUpdate(t, data) => self.update_stream(t, &data),
-						TxMessage::StreamRemove(t, ind) => self.remove_stream(t, ind),
-						TxMessage::CardUpdate(data) => self.update_card(&data),
-						TxMessage::CardRemove(ind) => self.remove_card(ind),
-						TxMessage::Peak(t, ind, peak) => self.update_peak(t, ind, peak),
+	fn log_message(&mut self, message: &TxMessage) {
+		let ( kind, description ) = match message {
+			TxMessage::Connection(state) => ( EventKind::Connection, match state {
+				ConnectionState::Connecting => format!("Connecting to {}", self.server.as_deref().unwrap_or("the default server")),
+				ConnectionState::Ready => format!("Connected to {}", self.server.as_deref().unwrap_or("the default server")),
+				ConnectionState::Lost => "Connection lost".to_owned()
+			}),
+			TxMessage::Server(data) => match &self.store.server {
+				None => ( EventKind::Server, format!("Server is {} {} on {}", data.server_name, data.server_version, data.host_name) ),
+				Some(old) => {
+					let mut changes = Vec::new();
+					if old.default_sink != data.default_sink {
+						changes.push(format!("Default output device changed from {} to {}",
+							self.device_label(StreamType::Sink, &old.default_sink), self.device_label(StreamType::Sink, &data.default_sink)));
+					}
+					if old.default_source != data.default_source {
+						changes.push(format!("Default input device changed from {} to {}",
+							self.device_label(StreamType::Source, &old.default_source), self.device_label(StreamType::Source, &data.default_source)));
 					}
+					if changes.is_empty() { changes.push("Server information changed".to_owned()); }
+					( EventKind::Server, changes.join(", ") )
+				}
+			},
+			TxMessage::StreamUpdate(t, stream) => match self.describe_stream_update(*t, stream) {
+				Some(description) => ( Pulse::event_kind(*t), description ),
+				None => return
+			},
+			TxMessage::StreamRemove(t, index) => {
+				let old = self.store.get_stream(*t, *index).or_else(|| self.hidden.get(&( *t, *index )));
+				if old.map_or(false, |old| is_own_stream(&old.data.name, &old.app)) { return; }
+				( Pulse::event_kind(*t), format!("{} {} removed", Pulse::type_label(*t),
+					old.map_or_else(|| format!("#{}", index), |old| format!("\"{}\"", old.data.description))) )
+			},
+			TxMessage::CardUpdate(data) => ( EventKind::Card, match self.store.cards.get(&data.index) {
+				None => format!("Card \"{}\" added", data.name),
+				Some(old) if old.active_profile != data.active_profile => {
+					let profile = |name: &str| data.profiles.iter().find(|( n, _ )| n == name).map_or(name.to_owned(), |( _, desc )| desc.clone());
+					format!("Card \"{}\" switched profile from {} to {}", data.name, profile(&old.active_profile), profile(&data.active_profile))
 				},
-				_ => break
-			}
-		}
+				Some(_) => format!("Card \"{}\" changed", data.name)
+			}),
+			TxMessage::CardRemove(index) => ( EventKind::Card, format!("Card {} removed",
+				self.store.cards.get(index).map_or_else(|| format!("#{}", index), |card| format!("\"{}\"", card.name))) ),
+			TxMessage::ModuleUpdate(data) => ( EventKind::Module, match self.store.modules.get(&data.index) {
+				None if data.argument.is_empty() => format!("Module {} loaded", data.name),
+				None => format!("Module {} loaded with {}", data.name, data.argument),
+				Some(_) => format!("Module {} changed", data.name)
+			}),
+			TxMessage::ModuleRemove(index) => ( EventKind::Module, format!("Module {} unloaded",
+				self.store.modules.get(index).map_or_else(|| format!("#{}", index), |module| module.name.clone())) ),
+			TxMessage::Loopback(source, data) => ( EventKind::Module, format!("Listening to {} through {}",
+				self.stream_label(StreamType::Source, *source), self.stream_label(StreamType::Sink, data.sink)) ),
+			TxMessage::Error(error) => ( EventKind::Error, error.clone() ),
+			TxMessage::Peak(..) => return
+		};
 
-		received
+		self.store.events.push(kind, description);
 	}
 
 
 	/**
-	 * Closes the connection to the pulse server, and cleans up any dangling monitors.
-	 * After this operation, no other methods should be called, and the instance should be freed from memory.
+	 * Describes a stream update for the event log, listing the changes that are worth recording.
+	 * Returns None if there are none, or if the stream is one of Myxer's own.
+	 *
+	 * * `t`      - The type of the stream.
+	 * * `stream` - The stream's new data.
 	 */
 
-	pub fn cleanup(&mut self) {
-		while let Some((i, _)) = self.sinks.iter().next() { let i = *i; self.remove_stream(StreamType::Sink, i) }
-		while let Some((i, _)) = self.sink_inputs.iter().next() { let i = *i; self.remove_stream(StreamType::SinkInput, i) }
-		while let Some((i, _)) = self.sources.iter().next() { let i = *i; self.remove_stream(StreamType::Source, i) }
-		while let Some((i, _)) = self.source_outputs.iter().next() { let i = *i; self.remove_stream(StreamType::SourceOutput, i) }
-		
-		let mut mainloop = self.mainloop.borrow_mut();
-		mainloop.stop();
+	fn describe_stream_update(&self, t: StreamType, stream: &TxStreamData) -> Option<String> {
+		let data = &stream.data;
+		if is_own_stream(&data.name, &stream.app) { return None; }
+		let label = format!("{} \"{}\"", Pulse::type_label(t), data.description);
+
+		let old = match self.store.get_stream(t, data.index).or_else(|| self.hidden.get(&( t, data.index ))) {
+			Some(old) => old,
+			None if stream.new => return Some(format!("{} added", label)),
+			None => return Some(format!("{} found", label))
+		};
+
+		let mut changes = Vec::new();
+		if ( t == StreamType::SinkInput || t == StreamType::SourceOutput ) && old.monitor_index != stream.monitor_index {
+			let device_t = if t == StreamType::SinkInput { StreamType::Sink } else { StreamType::Source };
+			changes.push(format!("moved from {} to {}", self.stream_label(device_t, old.monitor_index), self.stream_label(device_t, stream.monitor_index)));
+		}
+		if old.data.muted != data.muted { changes.push(if data.muted { "muted".to_owned() } else { "unmuted".to_owned() }); }
+		if old.data.volume != data.volume && !self.is_ducked_volume(t, data.index, &data.volume) { changes.push(format!("volume changed to {}", format_percent(data.volume.avg()))); }
+		if old.data.active_port != data.active_port { changes.push(format!("port changed from {} to {}", old.data.active_port, data.active_port)); }
+		if old.data.state != data.state {
+			if let Some(state) = data.state { changes.push(format!("state changed to {}", state.name().to_lowercase())); }
+		}
+
+		if changes.is_empty() { None } else { Some(format!("{} {}", label, changes.join(", "))) }
 	}
 
 
 	/**
-	 * Updates the stored default sink and source to the ones identified.
-	 * This method is called by the update method, the names are provided by the pulse server.
+	 * Checks if a volume is one that ducking set on a stream, which isn't recorded in the event log.
 	 *
-	 * * `sink`   - The default sink.
-	 * * `source` - The default source.
+	 * * `t`      - The type of the stream.
+	 * * `index`  - The index of the stream.
+	 * * `volume` - The stream's volume.
 	 */
 
-	fn update_default(&mut self, sink: String, source: String) {
-		for (i, v) in &self.sinks {
-			if v.data.name == sink {
-				self.default_sink = *i;
-				self.active_sink = *i;
-				break;
-			}
-		}
-
-		for (i, v) in &self.sources {
-			if v.data.name == source {
-				self.default_source = *i;
-				self.active_source = *i;
-				break;
-			}
-		}
+	fn is_ducked_volume(&self, t: StreamType, index: u32, volume: &ChannelVolumes) -> bool {
+		t == StreamType::SinkInput && self.ducked.get(&index).map_or(false, |ducked| !ducked.released && ducked.applied == *volume)
 	}
 
 
 	/**
-	 * Updates a stream in the store, or creates a new one and begins monitoring the peaks.
-	 * This method is called by the update method, the data is provided by the pulse server.
+	 * Gets the event kind for a stream type, which is either a device or a stream.
 	 *
-	 * * `t`      - The type of stream to update.
-	 * * `stream` - The new stream's data.
+	 * * `t` - The stream type.
 	 */
 
-	fn update_stream(&mut self, t: StreamType, stream: &TxStreamData) {
-		let data = stream.data.clone();
-		let index = data.index;
+	fn event_kind(t: StreamType) -> EventKind {
+		match t {
+			StreamType::Sink | StreamType::Source => EventKind::Device,
+			StreamType::SinkInput | StreamType::SourceOutput => EventKind::Stream
+		}
+	}
 
-		let entry = match t {
-			StreamType::Sink => self.sinks.get_mut(&index),
-			StreamType::SinkInput => self.sink_inputs.get_mut(&index),
-			StreamType::Source => self.sources.get_mut(&index),
-			StreamType::SourceOutput => self.source_outputs.get_mut(&index),
-		};
 
-		if let Some(stream) = entry { stream.data = data; }
-		else {
-			let source_str = stream.monitor_index.to_string();
-			let monitor = self.create_monitor_stream(t, if t == StreamType::SinkInput { None } else { Some(&source_str) }, index);
-			let data = StreamData { data, peak: 0, repetitions: 0, monitor, monitor_index: stream.monitor_index };
-			match t {
-				StreamType::Sink => self.sinks.insert(index, data),
-				StreamType::SinkInput => self.sink_inputs.insert(index, data),
-				StreamType::Source => self.sources.insert(index, data),
-				StreamType::SourceOutput => self.source_outputs.insert(index, data)
-			};
+	/**
+	 * Gets the label of a stream type, as used in the event log.
+	 *
+	 * * `t` - The stream type.
+	 */
+
+	fn type_label(t: StreamType) -> &'static str {
+		match t {
+			StreamType::Sink => "Output device",
+			StreamType::SinkInput => "Playback stream",
+			StreamType::Source => "Input device",
+			StreamType::SourceOutput => "Recording stream"
 		}
 	}
 
 
 	/**
-	 * Removes a stream from the store, stopping the monitor, if there is one.
-	 * This method is called by the update method, the data is provided by the pulse server.
+	 * Gets a quoted description of a stream for the event log, or its index if it isn't known.
 	 *
-	 * * `t`     - The type of stream to remove.
-	 * * `index` - The index of the stream to remove.
+	 * * `t`     - The type of the stream.
+	 * * `index` - The index of the stream.
 	 */
 
-	fn remove_stream(&mut self, t: StreamType, index: u32) {
-		let stream_opt = match t {
-			StreamType::Sink => self.sinks.get_mut(&index),
-			StreamType::SinkInput => self.sink_inputs.get_mut(&index),
-			StreamType::Source => self.sources.get_mut(&index),
-			StreamType::SourceOutput => self.source_outputs.get_mut(&index),
-		};
+	fn stream_label(&self, t: StreamType, index: u32) -> String {
+		self.store.get_stream(t, index).or_else(|| self.hidden.get(&( t, index )))
+			.map_or_else(|| format!("#{}", index), |stream| format!("\"{}\"", stream.data.description))
+	}
 
-		if let Some(stream) = stream_opt {
-			let mut monitor = stream.monitor.borrow_mut();
-			let mut mainloop = self.mainloop.borrow_mut();
-			mainloop.lock();
-			if monitor.get_state().is_good() {
-				monitor.set_read_callback(None);
-				let _ = monitor.disconnect();
-			}
-			mainloop.unlock();
-		}
 
-		match t {
-			StreamType::Sink => self.sinks.remove(&index),
-			StreamType::SinkInput => self.sink_inputs.remove(&index),
-			StreamType::Source => self.sources.remove(&index),
-			StreamType::SourceOutput => self.source_outputs.remove(&index),
-		};
+	/**
+	 * Gets a quoted description of a device for the event log, or its name if it isn't known.
+	 *
+	 * * `t`    - The type of the device.
+	 * * `name` - The name of the device.
+	 */
+
+	fn device_label(&self, t: StreamType, name: &str) -> String {
+		self.store.streams(t).values().chain(self.hidden.iter().filter(|( key, _ )| key.0 == t).map(|( _, stream )| stream))
+			.find(|stream| stream.data.name == name)
+			.map_or_else(|| name.to_owned(), |stream| format!("\"{}\"", stream.data.description))
 	}
 
 
 	/**
-	 * Updates a stored stream's peak.
-	 * This method is called by the update method, the data is provided by a monitor stream.
-	 *
-	 * * `t`     - The type of stream to update.
-	 * * `index` - The index of the stream to update.
-	 * * `peak`  - The peak value to store.
+	 * Removes all streams and cards from the store, stopping their monitors and the test tone.
+	 * Called when the connection is lost, as well as on cleanup.
 	 */
 
-	fn update_peak(&mut self, t: StreamType, index: u32, peak: u32) {
-		match t {
-			StreamType::Sink => self.sinks.entry(index).and_modify(|e| e.peak = peak),
-			StreamType::SinkInput => self.sink_inputs.entry(index).and_modify(|e| e.peak = peak),
-			StreamType::Source => self.sources.entry(index).and_modify(|e| e.peak = peak),
-			StreamType::SourceOutput => self.source_outputs.entry(index).and_modify(|e| e.peak = peak)
-		};
+	fn clear(&mut self) {
+		for (_, monitor) in std::mem::take(&mut self.monitors) { self.disconnect_monitor(&monitor); }
+		self.stop_test_tone();
+		self.hidden.clear();
+		self.ducked.clear();
+		self.duck_level = 1.0;
+		self.store.clear();
 	}
 
 
 	/**
-	 * Creates a monitor stream for the stream specified, and returns it.
-	 * Panics if there's an error.
-	 * TODO: Don't panic.
-	 *
-	 * * `t`            - The type of stream to monitor.
-	 * * `source`       - The source string of the stream, if one is needed.
-	 * * `stream_index` - The index of the stream to monitor.
+	 * Lowers the playback streams while a stream that triggers ducking is playing, and restores them afterwards.
+	 * The volumes are moved gradually, in a few steps, and a stream's volume isn't moved again until the server
+	 * has reported the last one. Streams whose volume is changed by the user while they are ducked are left at
+	 * the volume they were set to.
 	 */
 
-	fn create_monitor_stream(&mut self, t: StreamType, source: Option<&str>, stream_index: u32) -> Shared<Stream> {
-		fn read_callback(stream: &mut Stream, t: StreamType, index: u32, tx: &Sender<TxMessage>) {
-			let mut raw_peak = 0.0;
-			while stream.readable_size().is_some() {
-				match stream.peek().unwrap() {
-					PeekResult::Hole(_) => stream.discard().unwrap(),
-					PeekResult::Data(b) => {
-						#[allow(clippy::transmute_ptr_to_ref)]
-						let buf = slice_as_array!(b, [u8; 4]).expect("Bad length.");
-						raw_peak = f32::from_le_bytes(*buf).max(raw_peak);
-						stream.discard().unwrap();
-					},
-					_ => break
-				}
+	fn update_ducking(&mut self) {
+		if self.store.state != ConnectionState::Ready { return; }
+
+		let ducking = &self.store.ducking;
+		let streams: Vec<( u32, ChannelVolumes, bool )> = self.store.sink_inputs.iter()
+			.chain(self.hidden.iter().filter(|( key, _ )| key.0 == StreamType::SinkInput).map(|( key, stream )| ( &key.1, stream )))
+			.map(|( index, stream )| ( *index, stream.data.volume, ducking.is_trigger(&stream.app) )).collect();
+
+		let active = ducking.enabled && streams.iter().any(|( _, _, trigger )| *trigger);
+		let target = if active { ducking.level() } else { 1.0 };
+		if self.ducked.is_empty() && !active { self.duck_level = 1.0; return; }
+
+		if self.duck_updated.elapsed() < DUCKING_STEP { return; }
+		self.duck_updated = Instant::now();
+
+		let step = DUCKING_STEP.as_secs_f64() / DUCKING_RAMP.as_secs_f64();
+		self.duck_level = if target < self.duck_level { (self.duck_level - step).max(target) } else { (self.duck_level + step).min(target) };
+
+		self.ducked.retain(|index, _| streams.iter().any(|( i, _, _ )| i == index));
+
+		let mut changes = Vec::new();
+		for ( index, volume, trigger ) in streams {
+			if trigger || (!active && !self.ducked.contains_key(&index)) { continue; }
+
+			let ducked = self.ducked.entry(index)
+				.or_insert_with(|| DuckedStream { volume, applied: volume, confirmed: true, released: false });
+			if ducked.released { continue; }
+
+			// Until the server reports the last volume, the stream still has the previous one.
+			if volume != ducked.applied {
+				if ducked.confirmed { ducked.released = true; }
+				continue;
 			}
-			let peak = (raw_peak.sqrt() * 65535.0 * 1.5).round() as u32;
-			tx.send(TxMessage::Peak(t, index, peak)).unwrap();
+			ducked.confirmed = true;
+
+			let mut scaled = ducked.volume;
+			for channel in scaled.get_mut() { channel.0 = (channel.0 as f64 * self.duck_level).round() as u32; }
+			if ducked.applied == scaled { continue; }
+
+			ducked.applied = scaled;
+			ducked.confirmed = false;
+			changes.push(( index, scaled ));
 		}
 
-		let attr = BufferAttr {
-			fragsize: 4,
-			maxlength: u32::MAX,
-			..Default::default()
-		};
-		
-		let spec = Spec { channels: 1, format: Format::F32le, rate: 30 };
+		for ( index, volume ) in changes { self.set_volume(StreamType::SinkInput, index, volume); }
+
+		// Streams are only forgotten once they have all been restored.
+		if !active && self.duck_level >= 1.0 && self.ducked.values().all(|ducked| ducked.released || ducked.applied == ducked.volume) {
+			self.ducked.clear();
+		}
+	}
+
+
+	/**
+	 * Restores the streams lowered by ducking to their volumes immediately, waiting for the server to restore them.
+	 * Called before disconnecting, so that streams aren't left ducked.
+	 */
+
+	fn restore_ducked(&mut self) {
+		let ducked = std::mem::take(&mut self.ducked);
+		self.duck_level = 1.0;
+		if self.store.state != ConnectionState::Ready || ducked.is_empty() { return; }
+
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		let operations: Vec<_> = ducked.iter().filter(|( _, ducked )| !ducked.released)
+			.map(|( index, ducked )| introspect.set_sink_input_volume(*index, &ducked.volume, None)).collect();
+		Pulse::wait_for(&mut mainloop, &operations);
+
+		mainloop.unlock();
+	}
+
+
+	/**
+	 * Waits for operations to complete, for up to DISCONNECT_TIMEOUT.
+	 * The mainloop must be locked, it is unlocked while waiting so that the operations can progress.
+	 *
+	 * * `mainloop`   - The locked mainloop.
+	 * * `operations` - The operations to wait for.
+	 */
+
+	fn wait_for<T: ?Sized>(mainloop: &mut Mainloop, operations: &[PulseOperation<T>]) {
+		let start = Instant::now();
+		while operations.iter().any(|op| op.get_state() == OperationState::Running) && start.elapsed() < DISCONNECT_TIMEOUT {
+			mainloop.unlock();
+			std::thread::sleep(Duration::from_millis(10));
+			mainloop.lock();
+		}
+	}
+
+
+	/**
+	 * Updates a stream in the store, or creates a new one.
+	 * If the stream's peaks are requested, a monitor is created for it. Hidden streams are set aside instead.
+	 * This method is called by the update method, the data is provided by the pulse server.
+	 *
+	 * * `t`      - The type of stream to update.
+	 * * `stream` - The new stream's data.
+	 */
+
+	fn update_stream(&mut self, t: StreamType, stream: &TxStreamData) {
+		let data = stream.data.clone();
+		let index = data.index;
+
+		// Hidden streams are set aside, so that showing them doesn't require another sweep.
+		if self.is_hidden(t, &data.name, &stream.app) {
+			if let Some(monitor) = self.monitors.remove(&(t, index)) { self.disconnect_monitor(&monitor); }
+			self.store.streams_mut(t).remove(&index);
+
+			let peak = vec![ 0; data.volume.len() as usize ];
+			self.hidden.insert((t, index), StreamData {
+				data, peak, repetitions: 0, monitor_index: stream.monitor_index, app: stream.app.clone(),
+				owner_module: stream.owner_module, client: stream.client, monitor_of: stream.monitor_of });
+			return;
+		}
+		self.hidden.remove(&(t, index));
+
+		// Streams created while running are restored to the volume their application last had,
+		// streams that already existed are left alone and just remembered.
+		let known = self.store.get_stream(t, index).is_some();
+		match self.store.rules.restore(t, &stream.app, &data.volume).filter(|_| stream.new && !known) {
+			Some((volumes, muted)) => {
+				self.set_volume(t, index, volumes);
+				if muted != data.muted { self.set_muted(t, index, muted); }
+			},
+			// The ducked volumes of streams aren't remembered, the volumes they are restored to are.
+			None if t == StreamType::SinkInput && self.ducked.get(&index).map_or(false, |ducked| !ducked.released) => {},
+			None => self.store.rules.record(t, &stream.app, &data.volume, data.muted)
+		}
+
+		if let Some(entry) = self.store.get_stream_mut(t, index) {
+			entry.app = stream.app.clone();
+			entry.owner_module = stream.owner_module;
+			entry.client = stream.client;
+			entry.monitor_of = stream.monitor_of;
+			let remonitor = entry.monitor_index != stream.monitor_index || entry.data.channel_map != data.channel_map;
+			entry.data = data;
+			if !remonitor { return; }
+
+			// The stream has been moved to another device or its channels have changed,
+			// so the monitor has to be recreated.
+			entry.monitor_index = stream.monitor_index;
+			if let Some(monitor) = self.monitors.remove(&(t, index)) { self.disconnect_monitor(&monitor); }
+		}
+		else {
+			let peak = vec![ 0; data.volume.len() as usize ];
+			self.store.streams_mut(t).insert(index, StreamData {
+				data, peak, repetitions: 0, monitor_index: stream.monitor_index,
+				app: stream.app.clone(), owner_module: stream.owner_module, client: stream.client, monitor_of: stream.monitor_of });
+		}
+
+		if self.monitored.contains(&(t, index)) {
+			let monitor = self.create_monitor_stream(t, stream.monitor_index, index, stream.data.channel_map);
+			self.monitors.insert((t, index), monitor);
+		}
+	}
+
+
+	/**
+	 * Creates monitors for the requested streams that don't have one,
+	 * and stops the monitors of streams that are no longer requested.
+	 * The peaks of streams that stop being monitored are reset.
+	 */
+
+	fn update_monitors(&mut self) {
+		let stale: Vec<(StreamType, u32)> = self.monitors.keys().filter(|key| !self.monitored.contains(key)).copied().collect();
+		for key in stale {
+			if let Some(monitor) = self.monitors.remove(&key) { self.disconnect_monitor(&monitor); }
+			if let Some(stream) = self.store.get_stream_mut(key.0, key.1) {
+				for peak in &mut stream.peak { *peak = 0; }
+			}
+		}
+
+		if self.store.state != ConnectionState::Ready { return; }
+
+		let missing: Vec<(StreamType, u32, u32, ChannelMap)> = self.monitored.iter()
+			.filter(|key| !self.monitors.contains_key(key))
+			.filter_map(|key| self.store.get_stream(key.0, key.1).map(|s| (key.0, key.1, s.monitor_index, s.data.channel_map)))
+			.collect();
+
+		for (t, index, monitor_index, map) in missing {
+			let monitor = self.create_monitor_stream(t, monitor_index, index, map);
+			self.monitors.insert((t, index), monitor);
+		}
+	}
+
+
+	/**
+	 * Checks if a stream should be hidden, either because it is one of Myxer's own streams,
+	 * because it is a monitor source, or because the hide list matches it.
+	 *
+	 * * `t`    - The type of the stream.
+	 * * `name` - The name of the stream.
+	 * * `app`  - The application properties of the stream.
+	 */
+
+	fn is_hidden(&self, t: StreamType, name: &str, app: &AppProps) -> bool {
+		is_own_stream(name, app)
+			|| (t == StreamType::Source && name.ends_with(MONITOR_SOURCE_SUFFIX) && !self.show_monitors)
+			|| (!self.show_hidden && self.store.hide_list.matches(t, name, app))
+	}
+
+
+	/**
+	 * Moves streams between the store and the hidden streams, after the filters or the settings have changed.
+	 * Devices that were active and are now hidden are replaced by the defaults.
+	 */
+
+	fn update_hidden(&mut self) {
+		self.hidden_version = self.store.hide_list.version;
+
+		let shown: Vec<(StreamType, u32)> = self.hidden.iter()
+			.filter(|( key, stream )| !self.is_hidden(key.0, &stream.data.name, &stream.app)).map(|( key, _ )| *key).collect();
+		for ( t, index ) in shown {
+			if let Some(stream) = self.hidden.remove(&( t, index )) { self.store.streams_mut(t).insert(index, stream); }
+		}
+
+		for t in [ StreamType::Sink, StreamType::SinkInput, StreamType::Source, StreamType::SourceOutput ] {
+			let hidden: Vec<u32> = self.store.streams(t).iter()
+				.filter(|( _, stream )| self.is_hidden(t, &stream.data.name, &stream.app)).map(|( index, _ )| *index).collect();
+			for index in hidden {
+				if let Some(monitor) = self.monitors.remove(&( t, index )) { self.disconnect_monitor(&monitor); }
+				if let Some(mut stream) = self.store.streams_mut(t).remove(&index) {
+					for peak in &mut stream.peak { *peak = 0; }
+					self.hidden.insert(( t, index ), stream);
+				}
+			}
+		}
+
+		if !self.store.sinks.contains_key(&self.store.active_sink) { self.store.active_sink = self.store.default_sink; }
+		if !self.store.sources.contains_key(&self.store.active_source) { self.store.active_source = self.store.default_source; }
+		self.update_monitors();
+	}
+
+
+	/**
+	 * Removes a stream from the store, stopping the monitor, if there is one.
+	 * This method is called by the update method, the data is provided by the pulse server.
+	 *
+	 * * `t`     - The type of stream to remove.
+	 * * `index` - The index of the stream to remove.
+	 */
+
+	fn remove_stream(&mut self, t: StreamType, index: u32) {
+		if let Some(monitor) = self.monitors.remove(&(t, index)) { self.disconnect_monitor(&monitor); }
+		self.store.streams_mut(t).remove(&index);
+		self.hidden.remove(&(t, index));
+	}
+
+
+	/**
+	 * Stops a monitor stream, unbinding its read callback.
+	 *
+	 * * `monitor` - The monitor stream to disconnect.
+	 */
+
+	fn disconnect_monitor(&self, monitor: &Shared<Stream>) {
+		let mut monitor = monitor.borrow_mut();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+		monitor.set_read_callback(None);
+		if monitor.get_state().is_good() { let _ = monitor.disconnect(); }
+		mainloop.unlock();
+	}
+
+
+	/**
+	 * Updates a stored stream's peak.
+	 * This method is called by the update method, the data is provided by a monitor stream.
+	 *
+	 * * `t`     - The type of stream to update.
+	 * * `index` - The index of the stream to update.
+	 * * `peak`  - The peak values to store, one per channel.
+	 */
+
+	fn update_peak(&mut self, t: StreamType, index: u32, peak: Vec<u32>) {
+		if let Some(stream) = self.store.get_stream_mut(t, index) { stream.peak = peak; }
+	}
+
+
+	/**
+	 * Creates a monitor stream for the stream specified, and returns it.
+	 * The monitor records with the stream's channel map, so that a peak is reported for each channel.
+	 * Panics if there's an error.
+	 * TODO: Don't panic.
+	 *
+	 * * `t`             - The type of stream to monitor.
+	 * * `monitor_index` - The index of the device the stream is connected to.
+	 * * `stream_index`  - The index of the stream to monitor.
+	 * * `map`           - The channel map of the stream to monitor.
+	 */
+
+	fn create_monitor_stream(&mut self, t: StreamType, monitor_index: u32, stream_index: u32, map: ChannelMap) -> Shared<Stream> {
+		fn read_callback(stream: &mut Stream, t: StreamType, index: u32, channels: usize, tx: &Sender<TxMessage>) {
+			let mut raw_peaks = vec![ 0.0f32; channels ];
+			while stream.readable_size().is_some() {
+				match stream.peek().unwrap() {
+					PeekResult::Hole(_) => stream.discard().unwrap(),
+					PeekResult::Data(b) => {
+						// Samples are interleaved, so the channel of a sample is its position within the frame.
+						for (i, sample) in b.chunks_exact(4).enumerate() {
+							#[allow(clippy::transmute_ptr_to_ref)]
+							let buf = slice_as_array!(sample, [u8; 4]).expect("Bad length.");
+							let channel = i % channels;
+							raw_peaks[channel] = f32::from_le_bytes(*buf).max(raw_peaks[channel]);
+						}
+						stream.discard().unwrap();
+					},
+					_ => break
+				}
+			}
+			let peaks = raw_peaks.iter().map(|raw_peak| (raw_peak.sqrt() * MAX_NATURAL_VOL as f32).round() as u32).collect();
+			tx.send(TxMessage::Peak(t, index, peaks)).unwrap();
+		}
+
+		let map = if map.is_valid() { map } else { *ChannelMap::default().init_mono() };
+		let channels = map.get().len() as u8;
+
+		let attr = BufferAttr {
+			fragsize: 4 * channels as u32,
+			maxlength: u32::MAX,
+			..Default::default()
+		};
+		
+		let spec = Spec { channels, format: Format::F32le, rate: 30 };
 		assert!(spec.is_valid());
 		
-		let stream = Shared::new(Stream::new(&mut self.context.borrow_mut(), "Peak Detect", &spec, None).unwrap());
+		let source_str = monitor_index.to_string();
+		let source = if t == StreamType::SinkInput { None } else { Some(source_str.as_str()) };
+
+		let stream = Shared::new(Stream::new(&mut self.context.borrow_mut(), PEAK_STREAM_NAME, &spec, Some(&map)).unwrap());
 		{
 			let mut stream_mut = stream.borrow_mut();
 			if t == StreamType::SinkInput {
@@ -719,13 +1075,48 @@ impl Pulse {
 
 			let stream_clone = stream.clone();
 			let txc = self.channel.tx.clone();
-			stream_mut.set_read_callback(Some(Box::new(move |_| read_callback(&mut stream_clone.borrow_mut(), t, stream_index, &txc))));
+			stream_mut.set_read_callback(Some(Box::new(move |_| read_callback(&mut stream_clone.borrow_mut(), t, stream_index, channels as usize, &txc))));
 		}
 
 		stream
 	}
 
 
+	/**
+	 * Creates the samples of the speaker test tone, a sine wave that fades in and out to avoid clicking.
+	 * The samples are mono, in the F32le format.
+	 */
+
+	fn create_test_tone() -> Vec<u8> {
+		let length = (TEST_TONE_RATE as f32 * TEST_TONE_DURATION.as_secs_f32()) as usize;
+		let fade = TEST_TONE_RATE as usize / 50;
+
+		(0..length).flat_map(|i| {
+			let envelope = (i.min(length - i) as f32 / fade as f32).min(1.0);
+			let phase = i as f32 / TEST_TONE_RATE as f32 * TEST_TONE_FREQUENCY * std::f32::consts::TAU;
+			(phase.sin() * envelope * 0.5).to_le_bytes()
+		}).collect()
+	}
+
+
+	/**
+	 * Stops the speaker test tone, if one is playing.
+	 */
+
+	fn stop_test_tone(&mut self) {
+		if let Some(stream) = self.test_tone.take() {
+			let mut mainloop = self.mainloop.borrow_mut();
+			mainloop.lock();
+			{
+				let mut stream = stream.borrow_mut();
+				stream.set_write_callback(None);
+				if stream.get_state().is_good() { let _ = stream.disconnect(); }
+			}
+			mainloop.unlock();
+		}
+	}
+
+
 	/**
 	 * Updates a card in the store, or creates a new one.
 	 * This method is called by the update method, the data is provided by the pulse server.
@@ -735,7 +1126,7 @@ impl Pulse {
 
 	fn update_card(&mut self, data: &CardData) {
 		let index = data.index;
-		self.cards.insert(index, data.clone());
+		self.store.cards.insert(index, data.clone());
 	}
 
 
@@ -747,6 +1138,474 @@ impl Pulse {
 	 */
 
 	fn remove_card(&mut self, index: u32) {
-		self.cards.remove(&index);
+		self.store.cards.remove(&index);
+	}
+
+
+	/**
+	 * Removes a module from the store, along with the loopback it provided, if it was one.
+	 * This method is called by the update method, the data is provided by the pulse server.
+	 *
+	 * * `index` - The index of the module to remove.
+	 */
+
+	fn remove_module(&mut self, index: u32) {
+		self.store.modules.remove(&index);
+		self.store.loopbacks.retain(|_, loopback| loopback.module != index);
+	}
+
+
+	/**
+	 * Unloads the loopback modules created by Myxer, waiting up to a second for the server to unload them,
+	 * so that sources don't keep playing after Myxer exits.
+	 */
+
+	fn unload_loopbacks(&mut self) {
+		if self.store.state != ConnectionState::Ready || self.store.loopbacks.is_empty() { return; }
+
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		let operations: Vec<_> = self.store.loopbacks.values()
+			.map(|loopback| introspect.unload_module(loopback.module, |_|())).collect();
+		Pulse::wait_for(&mut mainloop, &operations);
+
+		mainloop.unlock();
+		self.store.loopbacks.clear();
+	}
+}
+
+impl Backend for Pulse {
+	fn store(&self) -> &Store {
+		&self.store
+	}
+
+	fn store_mut(&mut self) -> &mut Store {
+		&mut self.store
+	}
+
+	fn connect(&mut self) {
+		self.log_message(&TxMessage::Connection(ConnectionState::Connecting));
+
+		let mut mainloop = self.mainloop.borrow_mut();
+		let mut ctx = self.context.borrow_mut();
+
+		let ctx_shr_ref = self.context.clone();
+		let tx = self.channel.tx.clone();
+
+		self.store.state = ConnectionState::Connecting;
+
+		mainloop.lock();
+
+		// TODO: Try to see if there's a way to avoid using unsafe? It's in the docs...  but...?
+		ctx.set_state_callback(Some(Box::new(move || {
+			match unsafe { (*ctx_shr_ref.as_ptr()).get_state() } {
+				ContextState::Ready => { let _ = tx.send(TxMessage::Connection(ConnectionState::Ready)); },
+				ContextState::Failed |
+				ContextState::Terminated => { let _ = tx.send(TxMessage::Connection(ConnectionState::Lost)); },
+				_ => {},
+			}
+		})));
+
+		if ctx.connect(self.server.as_deref(), CtxFlagSet::NOFLAGS, None).is_err() {
+			eprintln!("Failed to connect to the pulse server {}.", self.server.as_deref().unwrap_or("(default)"));
+			let _ = self.channel.tx.send(TxMessage::Connection(ConnectionState::Lost));
+		}
+
+		mainloop.unlock();
+	}
+
+	fn update(&mut self) -> bool {
+		let mut received = false;
+
+		if let Some(reconnect_at) = self.reconnect_at {
+			if Instant::now() >= reconnect_at {
+				self.reconnect();
+				received = true;
+			}
+		}
+
+		loop {
+			let res = self.channel.rx.try_recv();
+			match res {
+				Ok(res) => {
+					received = true;
+					self.log_message(&res);
+					match res {
+						TxMessage::Connection(state) => self.update_connection(state),
+						TxMessage::Server(data) => {
+							self.store.update_default(&data.default_sink, &data.default_source);
+							self.store.server = Some(data);
+						},
+						TxMessage::StreamUpdate(t, data) => self.update_stream(t, &data),
+						TxMessage::StreamRemove(t, ind) => self.remove_stream(t, ind),
+						TxMessage::CardUpdate(data) => self.update_card(&data),
+						TxMessage::CardRemove(ind) => self.remove_card(ind),
+						TxMessage::ModuleUpdate(data) => { self.store.modules.insert(data.index, data); },
+						TxMessage::ModuleRemove(ind) => self.remove_module(ind),
+						TxMessage::Loopback(source, data) => { self.store.loopbacks.insert(source, data); },
+						TxMessage::Error(error) => self.store.errors.push(error),
+						TxMessage::Peak(t, ind, peak) => self.update_peak(t, ind, peak),
+					}
+				},
+				_ => break
+			}
+		}
+
+		if self.store.hide_list.version != self.hidden_version {
+			self.update_hidden();
+			received = true;
+		}
+
+		self.update_ducking();
+
+		self.store.rules.save(false);
+		received
+	}
+
+	fn cleanup(&mut self) {
+		self.restore_ducked();
+		self.unload_loopbacks();
+		self.clear();
+		self.store.rules.save(true);
+
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.stop();
+	}
+
+	fn server(&self) -> Option<&str> {
+		self.server.as_deref()
+	}
+
+	fn set_server(&mut self, server: Option<String>) {
+		if server == self.server { return; }
+		self.server = server;
+
+		// Nothing to tear down if the connection hasn't been initiated yet.
+		if self.context.borrow().get_state() == ContextState::Unconnected { return; }
+
+		self.restore_ducked();
+		self.unload_loopbacks();
+		self.clear();
+		self.replace_context();
+
+		// Messages from the old server may still be queued, and would otherwise be mixed into the new server's state.
+		while self.channel.rx.try_recv().is_ok() {}
+
+		self.reconnect_at = None;
+		self.reconnect_delay = RECONNECT_DELAY_MIN;
+		self.connect();
+	}
+
+	fn set_monitored(&mut self, streams: HashSet<(StreamType, u32)>) {
+		if streams == self.monitored { return; }
+		self.monitored = streams;
+		self.update_monitors();
+	}
+
+	fn set_show_monitors(&mut self, show: bool) {
+		if show == self.show_monitors { return; }
+		self.show_monitors = show;
+		self.update_hidden();
+	}
+
+	fn set_show_hidden(&mut self, show: bool) {
+		if show == self.show_hidden { return; }
+		self.show_hidden = show;
+		self.update_hidden();
+	}
+
+	fn set_default_sink(&self, sink: u32) {
+		if let Some(sink) = self.store.sinks.get(&sink) {
+			let mut mainloop = self.mainloop.borrow_mut();
+			mainloop.lock();
+			self.context.borrow_mut().set_default_sink(&sink.data.name, |_|());
+			mainloop.unlock();
+		}
+	}
+
+	fn set_default_source(&self, source: u32) {
+		if let Some(source) = self.store.sources.get(&source) {
+			let mut mainloop = self.mainloop.borrow_mut();
+			mainloop.lock();
+			self.context.borrow_mut().set_default_source(&source.data.name, |_|());
+			mainloop.unlock();
+		}
+	}
+
+	fn set_volume(&self, t: StreamType, index: u32, volumes: ChannelVolumes) {
+		if self.store.state != ConnectionState::Ready { return; }
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		match t {
+			StreamType::Sink => { introspect.set_sink_volume_by_index(index, &volumes, None); },
+			StreamType::SinkInput => { introspect.set_sink_input_volume(index, &volumes, None); },
+			StreamType::Source => { introspect.set_source_volume_by_index(index, &volumes, None); },
+			StreamType::SourceOutput => { introspect.set_source_output_volume(index, &volumes, None); }
+		};
+
+		mainloop.unlock();
+	}
+
+	fn set_muted(&self, t: StreamType, index: u32, mute: bool) {
+		if self.store.state != ConnectionState::Ready { return; }
+
+		// If unmuting a stream that has been set to 0 volume, it should be reset to full.
+		if !mute {
+			if let Some(entry) = self.store.get_stream(t, index) {
+				if entry.data.volume.max().0 == 0 {
+					let mut volumes = ChannelVolumes::default();
+					volumes.set_len(entry.data.volume.len());
+					volumes.set(entry.data.volume.len(), Volume(MAX_NATURAL_VOL));
+					self.set_volume(t, index, volumes);
+				}
+			}
+		};
+
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		match t {
+			StreamType::Sink => { introspect.set_sink_mute_by_index(index, mute, None) },
+			StreamType::SinkInput => { introspect.set_sink_input_mute(index, mute, None) },
+			StreamType::Source => { introspect.set_source_mute_by_index(index, mute, None) },
+			StreamType::SourceOutput => { introspect.set_source_output_mute(index, mute, None) }
+		};
+
+		mainloop.unlock();
+	}
+
+	/**
+	 * Set's a sound card's profile.
+	 * This effects how the card behaves, and how the system can utilize it.
+	 *
+	 * * `index`   - The card index to update.
+	 * * `profile` - The profile name to update the card to.
+	 */
+	 
+	fn set_card_profile(&self, index: u32, profile: &str) {
+		if self.store.state != ConnectionState::Ready { return; }
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+		introspect.set_card_profile_by_index(index, profile, None);
+		mainloop.unlock();
+	}
+
+	fn move_stream(&self, t: StreamType, index: u32, device: u32) {
+		if self.store.state != ConnectionState::Ready { return; }
+
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		match t {
+			StreamType::SinkInput => { introspect.move_sink_input_by_index(index, device, None); },
+			StreamType::SourceOutput => { introspect.move_source_output_by_index(index, device, None); },
+			_ => ()
+		};
+
+		mainloop.unlock();
+	}
+
+	fn set_port(&self, t: StreamType, index: u32, port: &str) {
+		if self.store.state != ConnectionState::Ready { return; }
+
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		match t {
+			StreamType::Sink => { introspect.set_sink_port_by_index(index, port, None); },
+			StreamType::Source => { introspect.set_source_port_by_index(index, port, None); },
+			_ => ()
+		};
+
+		mainloop.unlock();
+	}
+
+	fn set_suspended(&self, t: StreamType, index: u32, suspend: bool) {
+		if self.store.state != ConnectionState::Ready { return; }
+
+		let name = match self.store.get_stream(t, index) { Some(device) => device.data.description.clone(), None => return };
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		let tx = self.channel.tx.clone();
+		let callback: Box<dyn FnMut(bool)> = Box::new(move |success| {
+			if !success { tx.send(TxMessage::Error(format!("Failed to {} {}.", if suspend { "suspend" } else { "resume" }, name))).unwrap(); }
+		});
+
+		match t {
+			StreamType::Sink => { introspect.suspend_sink_by_index(index, suspend, Some(callback)); },
+			StreamType::Source => { introspect.suspend_source_by_index(index, suspend, Some(callback)); },
+			_ => ()
+		};
+
+		mainloop.unlock();
+	}
+
+	fn kill_stream(&self, t: StreamType, index: u32) {
+		if self.store.state != ConnectionState::Ready { return; }
+
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		let tx = self.channel.tx.clone();
+		let callback = move |success: bool| {
+			if !success { tx.send(TxMessage::Error("Failed to disconnect the stream.".to_owned())).unwrap(); }
+		};
+
+		match t {
+			StreamType::SinkInput => { introspect.kill_sink_input(index, callback); },
+			StreamType::SourceOutput => { introspect.kill_source_output(index, callback); },
+			_ => ()
+		};
+
+		mainloop.unlock();
+	}
+
+	fn kill_client(&self, client: u32) {
+		if self.store.state != ConnectionState::Ready { return; }
+
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		let tx = self.channel.tx.clone();
+		introspect.kill_client(client, move |success| {
+			if !success { tx.send(TxMessage::Error("Failed to disconnect the client.".to_owned())).unwrap(); }
+		});
+
+		mainloop.unlock();
+	}
+
+	fn load_module(&self, name: &str, argument: &str) {
+		if self.store.state != ConnectionState::Ready { return; }
+
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		let tx = self.channel.tx.clone();
+		let module = name.to_owned();
+		introspect.load_module(name, argument, move |index| {
+			if index == u32::MAX { tx.send(TxMessage::Error(format!("Failed to load {}. Check that the module exists and its arguments are valid.", module))).unwrap(); }
+		});
+
+		mainloop.unlock();
+	}
+
+	fn unload_module(&self, index: u32) {
+		if self.store.state != ConnectionState::Ready { return; }
+
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		let tx = self.channel.tx.clone();
+		let module = self.store.modules.get(&index).map(|m| m.name.clone()).unwrap_or_else(|| format!("module #{}", index));
+		introspect.unload_module(index, move |success| {
+			if !success { tx.send(TxMessage::Error(format!("Failed to unload {}.", module))).unwrap(); }
+		});
+
+		mainloop.unlock();
+	}
+
+	fn start_loopback(&self, source: u32, sink: u32, latency: u32) {
+		if self.store.state != ConnectionState::Ready { return; }
+
+		let source_name = match self.store.sources.get(&source) { Some(s) => s.data.name.clone(), None => return };
+		let sink_name = match self.store.sinks.get(&sink) { Some(s) => s.data.name.clone(), None => return };
+		let previous = self.store.loopbacks.get(&source).map(|loopback| loopback.module);
+
+		let mut introspect = self.context.borrow().introspect();
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		if let Some(previous) = previous { introspect.unload_module(previous, |_|()); }
+
+		let tx = self.channel.tx.clone();
+		let arguments = format!("source={} sink={} latency_msec={} source_dont_move=true sink_dont_move=true",
+			source_name, sink_name, latency);
+		introspect.load_module("module-loopback", &arguments, move |module| {
+			if module == u32::MAX { tx.send(TxMessage::Error(format!("Failed to play {} through {}.", source_name, sink_name))).unwrap(); }
+			else { tx.send(TxMessage::Loopback(source, LoopbackData { module, sink, latency })).unwrap(); }
+		});
+
+		mainloop.unlock();
+	}
+
+	fn stop_loopback(&self, source: u32) {
+		if let Some(loopback) = self.store.loopbacks.get(&source) { self.unload_module(loopback.module); }
+	}
+
+	fn play_test_tone(&mut self, sink: u32, channel: usize) {
+		if self.store.state != ConnectionState::Ready { return; }
+
+		let ( sink_name, position ) = match self.store.sinks.get(&sink)
+			.and_then(|s| s.data.channel_map.get().get(channel).map(|p| ( s.data.name.clone(), *p ))) {
+			Some(sink) => sink,
+			None => return
+		};
+
+		self.stop_test_tone();
+
+		// The stream has a single channel, which is mapped to the position being tested.
+		let mut map = ChannelMap::default();
+		map.set_len(1);
+		map.get_mut()[0] = position;
+
+		let spec = Spec { channels: 1, format: Format::F32le, rate: TEST_TONE_RATE };
+		let stream = match Stream::new(&mut self.context.borrow_mut(), TEST_TONE_STREAM_NAME, &spec, Some(&map)) {
+			Some(stream) => Shared::new(stream),
+			None => {
+				self.store.errors.push(format!("Failed to play a test tone through {}.", sink_name));
+				return;
+			}
+		};
+
+		let mut mainloop = self.mainloop.borrow_mut();
+		mainloop.lock();
+
+		// The stream is only borrowed while the mainloop is locked, as the callbacks borrow it on the mainloop's thread.
+		{
+			let mut stream_mut = stream.borrow_mut();
+
+			// The tone is written as the server requests it, and the stream disconnects itself once it has played.
+			// The callbacks hold weak pointers to the stream, so that they don't keep it alive once it is replaced.
+			let samples = Pulse::create_test_tone();
+			let mut offset = 0;
+			let stream_weak = stream.downgrade();
+			stream_mut.set_write_callback(Some(Box::new(move |length| {
+				if offset >= samples.len() { return; }
+				let stream_rc = match stream_weak.upgrade() { Some(stream) => stream, None => return };
+				let mut stream = stream_rc.borrow_mut();
+				let end = (offset + length / 4 * 4).min(samples.len());
+				let _ = stream.write_copy(&samples[offset..end], 0, SeekMode::Relative);
+				offset = end;
+
+				if offset >= samples.len() {
+					let stream_weak = stream_weak.clone();
+					stream.drain(Some(Box::new(move |_| {
+						if let Some(stream) = stream_weak.upgrade() { let _ = stream.borrow_mut().disconnect(); }
+					})));
+				}
+			})));
+
+			if stream_mut.connect_playback(Some(&sink_name), None, StreamFlagSet::NOFLAGS, None, None).is_err() {
+				self.store.errors.push(format!("Failed to play a test tone through {}.", sink_name));
+			}
+		}
+
+		mainloop.unlock();
+
+		self.test_tone = Some(stream);
 	}
 }
@@ -0,0 +1,363 @@
+/*!
+ * Remembers the last volume of application streams, so that it can be restored when the application
+ * creates a new stream. Streams are identified by the application properties in their proplist.
+ */
+
+use std::time::{ Duration, Instant };
+
+use libpulse::volume::{ Volume, ChannelVolumes };
+
+use crate::backend::{ StreamType, APP_NAME };
+
+/** The minimum time between writes of the rules file, so that dragging a slider doesn't spam the disk. */
+const SAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+
+/**
+ * The application properties of a stream, taken from its proplist.
+ */
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AppProps {
+	/** The `application.name` property. */
+	pub name: String,
+	/** The `application.process.binary` property. */
+	pub binary: String,
+	/** The `application.process.id` property. */
+	pub process_id: String,
+	/** The `application.process.host` property, the host the process runs on. */
+	pub process_host: String,
+	/** The `media.role` property. */
+	pub role: String,
+}
+
+
+/**
+ * A remembered volume for an application's streams.
+ */
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+	pub t: StreamType,
+
+	pub name: String,
+	pub binary: String,
+	pub role: String,
+
+	pub volume: u32,
+	pub muted: bool,
+}
+
+impl Rule {
+
+	/**
+	 * Checks if the rule applies to the stream specified.
+	 *
+	 * * `t`   - The type of the stream.
+	 * * `app` - The application properties of the stream.
+	 */
+
+	pub fn matches(&self, t: StreamType, app: &AppProps) -> bool {
+		self.t == t && self.name == app.name && self.binary == app.binary && self.role == app.role
+	}
+}
+
+
+/**
+ * Stores the remembered volumes, and persists them to a file in the config directory.
+ */
+
+pub struct Rules {
+	pub rules: Vec<Rule>,
+
+	/** Incremented every time the rules change, so that views can tell when to refresh. */
+	pub version: u32,
+
+	path: Option<std::path::PathBuf>,
+	dirty: bool,
+	last_save: Option<Instant>,
+}
+
+impl Rules {
+
+	/**
+	 * Creates an empty rules store, which isn't persisted.
+	 */
+
+	pub fn new() -> Self {
+		Rules { rules: Vec::new(), version: 0, path: None, dirty: false, last_save: None }
+	}
+
+
+	/**
+	 * Loads the rules from the rules file in the config directory.
+	 * If the file doesn't exist or is invalid, an empty store is returned, which will be saved to that file.
+	 */
+
+	pub fn load() -> Self {
+		let mut rules = Rules::new();
+
+		if let Some(home_dir) = std::env::var_os("HOME") {
+			let mut path = std::path::PathBuf::new();
+			path.push(&home_dir);
+			path.push(".config");
+			path.push("myxer");
+			path.push("rules.conf");
+
+			if let Ok(content) = std::fs::read_to_string(&path) {
+				rules.rules = content.lines().filter_map(Rules::parse_line).collect();
+			}
+
+			rules.path = Some(path);
+		}
+
+		rules
+	}
+
+
+	/**
+	 * Parses a line of the rules file, returning None if it is invalid.
+	 * Lines contain the stream type, name, binary, role, volume, and muted state, separated by tabs.
+	 */
+
+	fn parse_line(line: &str) -> Option<Rule> {
+		let parts: Vec<&str> = line.split('\t').collect();
+		if parts.len() != 6 { return None; }
+
+		Some(Rule {
+			t: match parts[0] { "output" => StreamType::SinkInput, "input" => StreamType::SourceOutput, _ => return None },
+			name: parts[1].to_owned(),
+			binary: parts[2].to_owned(),
+			role: parts[3].to_owned(),
+			volume: parts[4].parse().ok()?,
+			muted: parts[5].parse().ok()?
+		})
+	}
+
+
+	/**
+	 * Formats a rule as a line of the rules file, as parsed by `parse_line`.
+	 *
+	 * * `rule` - The rule to format.
+	 */
+
+	fn format_line(rule: &Rule) -> String {
+		format!("{}\t{}\t{}\t{}\t{}\t{}\n",
+			if rule.t == StreamType::SinkInput { "output" } else { "input" },
+			rule.name, rule.binary, rule.role, rule.volume, rule.muted)
+	}
+
+
+	/**
+	 * Writes the rules to the rules file, if they have changed since they were last written.
+	 *
+	 * * `force` - Whether to ignore the minimum time between writes.
+	 */
+
+	pub fn save(&mut self, force: bool) {
+		if !self.dirty { return; }
+		if !force && self.last_save.map_or(false, |last| last.elapsed() < SAVE_INTERVAL) { return; }
+
+		let path = match &self.path { Some(path) => path, None => return };
+
+		let content: String = self.rules.iter().map(Rules::format_line).collect();
+
+		if let Some(dir) = path.parent() { let _ = std::fs::create_dir_all(dir); }
+		if let Err(err) = std::fs::write(path, content) { eprintln!("Failed to save remembered volumes: {}", err); }
+
+		self.dirty = false;
+		self.last_save = Some(Instant::now());
+	}
+
+
+	/**
+	 * Finds the rule for the stream specified, if there is one.
+	 *
+	 * * `t`   - The type of the stream.
+	 * * `app` - The application properties of the stream.
+	 */
+
+	pub fn find(&self, t: StreamType, app: &AppProps) -> Option<&Rule> {
+		self.rules.iter().find(|rule| rule.matches(t, app))
+	}
+
+
+	/**
+	 * Records the current volume of a stream, creating a rule for it if there isn't one.
+	 * Streams that aren't application streams, that don't identify their application, or that are Myxer's own, are ignored.
+	 *
+	 * * `t`       - The type of the stream.
+	 * * `app`     - The application properties of the stream.
+	 * * `volumes` - The current volumes of the stream.
+	 * * `muted`   - Whether the stream is muted.
+	 */
+
+	pub fn record(&mut self, t: StreamType, app: &AppProps, volumes: &ChannelVolumes, muted: bool) {
+		if t != StreamType::SinkInput && t != StreamType::SourceOutput { return; }
+		if app.name.is_empty() && app.binary.is_empty() { return; }
+		if app.name == APP_NAME { return; }
+
+		let volume = volumes.max().0;
+
+		match self.rules.iter_mut().find(|rule| rule.matches(t, app)) {
+			Some(rule) => {
+				if rule.volume == volume && rule.muted == muted { return; }
+				rule.volume = volume;
+				rule.muted = muted;
+			},
+			None => self.rules.push(Rule {
+				t, name: app.name.clone(), binary: app.binary.clone(), role: app.role.clone(), volume, muted
+			})
+		}
+
+		self.dirty = true;
+		self.version += 1;
+	}
+
+
+	/**
+	 * Gets the volumes and muted state a new stream should be restored to, if there is a rule for it.
+	 * The stream's channel balance is preserved.
+	 *
+	 * * `t`       - The type of the stream.
+	 * * `app`     - The application properties of the stream.
+	 * * `volumes` - The volumes the stream was created with.
+	 */
+
+	pub fn restore(&self, t: StreamType, app: &AppProps, volumes: &ChannelVolumes) -> Option<(ChannelVolumes, bool)> {
+		let rule = self.find(t, app)?;
+		let mut volumes = *volumes;
+		volumes.scale(Volume(rule.volume));
+		Some((volumes, rule.muted))
+	}
+
+
+	/**
+	 * Removes a rule.
+	 *
+	 * * `index` - The position of the rule to remove.
+	 */
+
+	pub fn remove(&mut self, index: usize) {
+		if index >= self.rules.len() { return; }
+		self.rules.remove(index);
+		self.dirty = true;
+		self.version += 1;
+		self.save(true);
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use libpulse::volume::{ Volume, ChannelVolumes };
+
+	use crate::backend::{ StreamType, APP_NAME };
+	use super::{ AppProps, Rule, Rules };
+
+
+	/**
+	 * Creates the application properties of a stream.
+	 */
+
+	fn app(name: &str, binary: &str, role: &str) -> AppProps {
+		AppProps { name: name.to_owned(), binary: binary.to_owned(), process_id: "1234".to_owned(), role: role.to_owned(), ..AppProps::default() }
+	}
+
+
+	/**
+	 * Creates stereo volumes with the volumes of each channel.
+	 */
+
+	fn volumes(left: u32, right: u32) -> ChannelVolumes {
+		let mut volumes = ChannelVolumes::default();
+		volumes.set_len(2);
+		volumes.get_mut()[0] = Volume(left);
+		volumes.get_mut()[1] = Volume(right);
+		volumes
+	}
+
+
+	#[test]
+	fn rules_match_type_and_application() {
+		let rule = Rule { t: StreamType::SinkInput, name: "Firefox".to_owned(), binary: "firefox".to_owned(),
+			role: String::new(), volume: 30000, muted: false };
+
+		assert!(rule.matches(StreamType::SinkInput, &app("Firefox", "firefox", "")));
+		assert!(!rule.matches(StreamType::SourceOutput, &app("Firefox", "firefox", "")));
+		assert!(!rule.matches(StreamType::SinkInput, &app("Firefox", "firefox", "music")));
+		assert!(!rule.matches(StreamType::SinkInput, &app("Firefox", "firefox-bin", "")));
+		assert!(!rule.matches(StreamType::SinkInput, &app("firefox", "firefox", "")));
+	}
+
+
+	#[test]
+	fn lines_round_trip() {
+		let rule = Rule { t: StreamType::SourceOutput, name: "Zoom Meeting".to_owned(), binary: "zoom".to_owned(),
+			role: "phone".to_owned(), volume: 65536, muted: true };
+
+		let line = Rules::format_line(&rule);
+		assert_eq!(line, "input\tZoom Meeting\tzoom\tphone\t65536\ttrue\n");
+		assert_eq!(Rules::parse_line(line.trim_end_matches('\n')), Some(rule));
+
+		let rule = Rule { t: StreamType::SinkInput, name: String::new(), binary: "mpv".to_owned(),
+			role: String::new(), volume: 0, muted: false };
+		assert_eq!(Rules::parse_line(Rules::format_line(&rule).trim_end_matches('\n')), Some(rule));
+	}
+
+
+	#[test]
+	fn invalid_lines_are_skipped() {
+		assert_eq!(Rules::parse_line(""), None);
+		assert_eq!(Rules::parse_line("output\tmpv\tmpv\t\t100"), None);
+		assert_eq!(Rules::parse_line("output\tmpv\tmpv\t\t100\tfalse\textra"), None);
+		assert_eq!(Rules::parse_line("sink\tmpv\tmpv\t\t100\tfalse"), None);
+		assert_eq!(Rules::parse_line("output\tmpv\tmpv\t\tloud\tfalse"), None);
+		assert_eq!(Rules::parse_line("output\tmpv\tmpv\t\t100\tno"), None);
+	}
+
+
+	#[test]
+	fn record_updates_one_rule_per_application() {
+		let mut rules = Rules::new();
+		rules.record(StreamType::SinkInput, &app("mpv", "mpv", ""), &volumes(40000, 20000), false);
+		rules.record(StreamType::SinkInput, &app("mpv", "mpv", ""), &volumes(50000, 50000), true);
+		rules.record(StreamType::SourceOutput, &app("mpv", "mpv", ""), &volumes(10000, 10000), false);
+
+		assert_eq!(rules.rules.len(), 2);
+		let rule = rules.find(StreamType::SinkInput, &app("mpv", "mpv", "")).unwrap();
+		assert_eq!(( rule.volume, rule.muted ), ( 50000, true ));
+		assert_eq!(rules.version, 3);
+
+		rules.record(StreamType::SinkInput, &app("mpv", "mpv", ""), &volumes(50000, 50000), true);
+		assert_eq!(rules.version, 3);
+	}
+
+
+	#[test]
+	fn record_ignores_devices_and_unidentified_streams() {
+		let mut rules = Rules::new();
+		rules.record(StreamType::Sink, &app("mpv", "mpv", ""), &volumes(1, 1), false);
+		rules.record(StreamType::SinkInput, &app("", "", "music"), &volumes(1, 1), false);
+		rules.record(StreamType::SinkInput, &app(APP_NAME, "myxer", ""), &volumes(1, 1), false);
+		rules.record(StreamType::SourceOutput, &app(APP_NAME, "myxer", ""), &volumes(1, 1), false);
+
+		assert!(rules.rules.is_empty());
+		assert_eq!(rules.version, 0);
+	}
+
+
+	#[test]
+	fn restore_keeps_the_channel_balance() {
+		let mut rules = Rules::new();
+		assert_eq!(rules.restore(StreamType::SinkInput, &app("mpv", "mpv", ""), &volumes(65536, 65536)), None);
+
+		rules.record(StreamType::SinkInput, &app("mpv", "mpv", ""), &volumes(30000, 30000), true);
+		let ( restored, muted ) = rules.restore(StreamType::SinkInput, &app("mpv", "mpv", ""), &volumes(65536, 32768)).unwrap();
+
+		assert!(muted);
+		assert_eq!(restored.get()[0], Volume(30000));
+		assert_eq!(restored.get()[1], Volume(15000));
+		assert_eq!(rules.restore(StreamType::SourceOutput, &app("mpv", "mpv", ""), &volumes(65536, 65536)), None);
+	}
+}
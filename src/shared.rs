@@ -3,7 +3,7 @@
  * Shamelessly copied from https://gist.github.com/stevedonovan/7e3a6d8c8921e3eff16c4b11ab82b8d7.
  */
 
-use std::rc::Rc;
+use std::rc::{ Rc, Weak };
 use std::cell::{RefCell, Ref, RefMut};
 use std::ops::Deref;
 use std::fmt;
@@ -15,7 +15,7 @@ use std::fmt;
  */
 
 #[derive(Clone)]
-pub struct Shared<T> {
+pub struct Shared<T: ?Sized> {
 	v: Rc<RefCell<T>>
 }
 
@@ -28,9 +28,28 @@ impl <T> Shared<T> {
 	pub fn new(t: T) -> Shared<T> {
 		Shared { v: Rc::new(RefCell::new(t)) }
 	}
+
+
+	/**
+	 * Replaces the stored object with a new one.
+	 */
+
+	pub fn replace(&self, t: T) -> T {
+		self.v.replace(t)
+	}
 }
 
-impl <T> Shared<T> {
+impl <T: ?Sized> Shared<T> {
+
+	/**
+	 * Creates a new Shared from an existing shared pointer.
+	 * This allows a Shared trait object to be created from a concrete type.
+	 */
+
+	pub fn from_rc(v: Rc<RefCell<T>>) -> Shared<T> {
+		Shared { v }
+	}
+
 
 	/**
 	 * Borrows an immutable reference to the stored object.
@@ -60,11 +79,12 @@ impl <T> Shared<T> {
 
 
 	/**
-	 * Replaces the stored object with a new one.
+	 * Creates a weak pointer to the stored memory, which doesn't keep the object alive.
+	 * This allows an object to be referenced by callbacks that it owns.
 	 */
 
-	pub fn replace(&self, t: T) -> T {
-		self.v.replace(t)
+	pub fn downgrade(&self) -> Weak<RefCell<T>> {
+		Rc::downgrade(&self.v)
 	}
 
 
@@ -0,0 +1,80 @@
+/*!
+ * Contains the Connect to Server window.
+ */
+
+use gtk::prelude::*;
+use gio::prelude::*;
+use glib::ToVariant;
+
+use crate::backend::Backend;
+use crate::shared::Shared;
+
+
+/**
+ * The Connect to Server popup window.
+ * Allows entering the server to connect to, which is switched to using the `set_server` window action.
+ */
+
+pub struct ConnectServer {
+	/** Indicates if the popup should remain open. */
+	live: Shared<bool>
+}
+
+impl ConnectServer {
+
+	/**
+	 * Creates the Connect to Server window, and its contents.
+	 * The entry is filled with the server that is currently connected to.
+	 */
+
+	pub fn new(parent: &gtk::ApplicationWindow, pulse: &Shared<dyn Backend>) -> Self {
+		let dialog = gtk::Dialog::with_buttons(Some("Connect to Server"), Some(parent), gtk::DialogFlags::all(),
+			&[ ( "Cancel", gtk::ResponseType::Cancel ), ( "Connect", gtk::ResponseType::Accept ) ]);
+		dialog.set_default_response(gtk::ResponseType::Accept);
+		dialog.set_resizable(false);
+
+		let live = Shared::new(true);
+		let live_clone = live.clone();
+		dialog.connect_close(move |_| { live_clone.replace(false); });
+
+		let content = gtk::Box::new(gtk::Orientation::Vertical, 6);
+		content.set_border_width(12);
+		dialog.get_content_area().pack_start(&content, true, true, 0);
+
+		let server = gtk::Entry::new();
+		server.set_placeholder_text(Some("Default Server"));
+		server.set_text(pulse.borrow().server().unwrap_or(""));
+		server.set_activates_default(true);
+		server.set_width_chars(40);
+		content.pack_start(&server, false, false, 0);
+
+		let hint = gtk::Label::new(Some("A socket path, like unix:/run/user/1000/pulse/native, or tcp:host:port.\nLeave empty to connect to the default server."));
+		hint.set_halign(gtk::Align::Start);
+		hint.get_style_context().add_class("dim-label");
+		content.pack_start(&hint, false, false, 0);
+
+		let parent = parent.clone();
+		dialog.connect_response(move |dialog, response| {
+			if response == gtk::ResponseType::Accept {
+				if let Some(actions) = parent.get_action_group("app") {
+					actions.activate_action("set_server", Some(&server.get_text().trim().to_variant()));
+				}
+			}
+
+			dialog.emit_close();
+		});
+
+		dialog.show_all();
+
+		Self { live }
+	}
+
+
+	/**
+	 * Returns a boolean indicating if the window should continue to be open or not.
+	 */
+
+	pub fn update(&mut self) -> bool {
+		*self.live.borrow()
+	}
+}
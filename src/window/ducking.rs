@@ -0,0 +1,97 @@
+/*!
+ * Contains the Ducking window.
+ */
+
+use gtk::prelude::*;
+
+use crate::backend::Backend;
+use crate::shared::Shared;
+
+
+/**
+ * The Ducking popup window.
+ * Configures whether the other playback streams are lowered during calls, by how much, and which applications trigger it.
+ */
+
+pub struct Ducking {
+	/** Indicates if the popup should remain open. */
+	live: Shared<bool>
+}
+
+impl Ducking {
+
+	/**
+	 * Creates the Ducking window, and its contents. Changes are applied immediately.
+	 */
+
+	pub fn new(parent: &gtk::ApplicationWindow, pulse: &Shared<dyn Backend>) -> Self {
+		let dialog = gtk::Dialog::with_buttons(Some("Ducking"), Some(parent), gtk::DialogFlags::all(), &[ ( "Close", gtk::ResponseType::Close ) ]);
+		dialog.set_resizable(false);
+
+		let live = Shared::new(true);
+		dialog.connect_response(|s, _| s.emit_close());
+		let live_clone = live.clone();
+		dialog.connect_close(move |_| { live_clone.replace(false); });
+
+		let grid = gtk::Grid::new();
+		grid.set_border_width(12);
+		grid.set_row_spacing(6);
+		grid.set_column_spacing(12);
+		dialog.get_content_area().pack_start(&grid, true, true, 0);
+
+		let pulse_ref = pulse.borrow();
+		let settings = &pulse_ref.store().ducking;
+
+		let enabled = gtk::Switch::new();
+		enabled.set_active(settings.enabled);
+		enabled.set_halign(gtk::Align::Start);
+		let pulse_clone = pulse.clone();
+		enabled.connect_property_active_notify(move |s| pulse_clone.borrow_mut().store_mut().ducking.set_enabled(s.get_active()));
+
+		let amount = gtk::SpinButton::with_range(5.0, 100.0, 5.0);
+		amount.set_value(settings.amount as f64);
+		amount.set_halign(gtk::Align::Start);
+		let pulse_clone = pulse.clone();
+		amount.connect_value_changed(move |s| pulse_clone.borrow_mut().store_mut().ducking.set_amount(s.get_value_as_int() as u32));
+
+		let apps = gtk::Entry::new();
+		apps.set_text(&settings.apps.join(", "));
+		apps.set_placeholder_text(Some("zoom, discord"));
+		apps.set_width_chars(30);
+		let pulse_clone = pulse.clone();
+		apps.connect_changed(move |s| pulse_clone.borrow_mut().store_mut().ducking.set_apps(crate::ducking::Ducking::parse_apps(&s.get_text())));
+
+		let rows: [( &str, gtk::Widget ); 3] = [
+			( "Lower Other Streams", enabled.upcast() ),
+			( "Lower By (%)", amount.upcast() ),
+			( "Applications", apps.upcast() )
+		];
+
+		for (i, ( label, widget )) in rows.iter().enumerate() {
+			let label = gtk::Label::new(Some(label));
+			label.set_halign(gtk::Align::End);
+			label.get_style_context().add_class("dim-label");
+			grid.attach(&label, 0, i as i32, 1, 1);
+			grid.attach(widget, 1, i as i32, 1, 1);
+		}
+
+		let hint = gtk::Label::new(Some("Streams with the phone role, and streams of the applications listed,\nseparated by commas, lower the other playback streams while they play."));
+		hint.set_halign(gtk::Align::Start);
+		hint.get_style_context().add_class("dim-label");
+		grid.attach(&hint, 0, rows.len() as i32, 2, 1);
+
+		drop(pulse_ref);
+		dialog.show_all();
+
+		Self { live }
+	}
+
+
+	/**
+	 * Returns a boolean indicating if the window should continue to be open or not.
+	 */
+
+	pub fn update(&mut self) -> bool {
+		*self.live.borrow()
+	}
+}
@@ -0,0 +1,191 @@
+/*!
+ * Contains the Event Log window.
+ */
+
+use gtk::prelude::*;
+
+use crate::backend::Backend;
+use crate::events::EventKind;
+use crate::shared::Shared;
+
+/** The columns of the event list, which hold the time, kind label, and description of each event. */
+const COLUMNS: [( u32, &str ); 3] = [ ( 0, "Time" ), ( 1, "Kind" ), ( 2, "Description" ) ];
+
+
+/**
+ * The Event Log popup window.
+ * Lists the server's activity, newest first, and allows filtering it, clearing it, and exporting it to a text file.
+ */
+
+pub struct EventLog {
+	list: gtk::ListStore,
+	pulse: Shared<dyn Backend>,
+
+	/** The version of the event log that the list was last updated to. */
+	version: u32,
+
+	/** Indicates if the popup should remain open. */
+	live: Shared<bool>
+}
+
+impl EventLog {
+
+	/**
+	 * Creates the Event Log window, and its contents.
+	 */
+
+	pub fn new(parent: &gtk::ApplicationWindow, pulse: &Shared<dyn Backend>) -> Self {
+		let dialog = gtk::Dialog::with_buttons(Some("Event Log"), Some(parent), gtk::DialogFlags::all(), &[]);
+		dialog.set_border_width(0);
+
+		let live = Shared::new(true);
+		dialog.connect_response(|s, _| s.emit_close());
+		let live_clone = live.clone();
+		dialog.connect_close(move |_| { live_clone.replace(false); });
+
+		let geom = gdk::Geometry {
+			min_width: 650, min_height: 400,
+			max_width: 10000, max_height: 10000,
+			base_width: -1, base_height: -1,
+			width_inc: -1, height_inc: -1,
+			min_aspect: 0.0, max_aspect: 0.0,
+			win_gravity: gdk::Gravity::Center
+		};
+
+		dialog.set_geometry_hints::<gtk::Dialog>(None, Some(&geom), gdk::WindowHints::MIN_SIZE);
+		dialog.get_content_area().set_border_width(0);
+
+		let error_bar = gtk::InfoBar::new();
+		error_bar.set_message_type(gtk::MessageType::Error);
+		error_bar.set_show_close_button(true);
+		error_bar.set_no_show_all(true);
+		error_bar.connect_response(|bar, _| bar.hide());
+		let error_label = gtk::Label::new(None);
+		error_label.set_line_wrap(true);
+		error_label.show();
+		error_bar.get_content_area().add(&error_label);
+		dialog.get_content_area().pack_start(&error_bar, false, false, 0);
+
+		let filter_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+		filter_box.set_border_width(6);
+
+		let kind = gtk::ComboBoxText::new();
+		kind.append(Some(""), "All Events");
+		for choice in &EventKind::ALL { kind.append(Some(choice.label()), choice.label()); }
+		kind.set_active(Some(0));
+		filter_box.pack_start(&kind, false, false, 3);
+
+		let search = gtk::SearchEntry::new();
+		search.set_placeholder_text(Some("Filter"));
+		filter_box.pack_start(&search, true, true, 3);
+
+		let clear = gtk::Button::with_label("Clear");
+		filter_box.pack_start(&clear, false, false, 3);
+
+		let export = gtk::Button::with_label("Export...");
+		filter_box.pack_start(&export, false, false, 3);
+
+		dialog.get_content_area().pack_start(&filter_box, false, false, 0);
+		dialog.get_content_area().pack_start(&gtk::Separator::new(gtk::Orientation::Horizontal), false, false, 0);
+
+		let list = gtk::ListStore::new(&[ glib::Type::String, glib::Type::String, glib::Type::String ]);
+		let filter = gtk::TreeModelFilter::new(&list, None);
+
+		let kind_clone = kind.clone();
+		let search_clone = search.clone();
+		filter.set_visible_func(move |model, iter| {
+			let value = |column: u32| model.get_value(iter, column as i32).get::<String>().ok().flatten().unwrap_or_default();
+
+			let kind = kind_clone.get_active_id().map(|id| id.to_string()).unwrap_or_default();
+			if !kind.is_empty() && value(1) != kind { return false; }
+
+			let text = search_clone.get_text().to_lowercase();
+			text.is_empty() || value(2).to_lowercase().contains(text.trim())
+		});
+
+		let filter_clone = filter.clone();
+		kind.connect_changed(move |_| filter_clone.refilter());
+		let filter_clone = filter.clone();
+		search.connect_search_changed(move |_| filter_clone.refilter());
+
+		let view = gtk::TreeView::with_model(&filter);
+		for ( column, title ) in &COLUMNS {
+			let cell = gtk::CellRendererText::new();
+			let view_column = gtk::TreeViewColumn::new();
+			view_column.set_title(title);
+			view_column.pack_start(&cell, true);
+			view_column.add_attribute(&cell, "text", *column as i32);
+			view_column.set_resizable(true);
+			view.append_column(&view_column);
+		}
+
+		let scroller = gtk::ScrolledWindow::new::<gtk::Adjustment, gtk::Adjustment>(None, None);
+		scroller.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+		dialog.get_content_area().pack_start(&scroller, true, true, 0);
+		scroller.add(&view);
+
+		let pulse_clone = pulse.clone();
+		let list_clone = list.clone();
+		clear.connect_clicked(move |_| {
+			pulse_clone.borrow_mut().store_mut().events.clear();
+			list_clone.clear();
+		});
+
+		let pulse_clone = pulse.clone();
+		let dialog_clone = dialog.clone();
+		export.connect_clicked(move |_| {
+			let chooser = gtk::FileChooserDialog::with_buttons(Some("Export Event Log"), Some(&dialog_clone), gtk::FileChooserAction::Save,
+				&[ ( "Cancel", gtk::ResponseType::Cancel ), ( "Export", gtk::ResponseType::Accept ) ]);
+			chooser.set_current_name("myxer-events.txt");
+			chooser.set_do_overwrite_confirmation(true);
+
+			if chooser.run() == gtk::ResponseType::Accept {
+				if let Some(path) = chooser.get_filename() {
+					if let Err(err) = pulse_clone.borrow().store().events.export(&path) {
+						error_label.set_label(&format!("Failed to export the event log: {}", err));
+						error_bar.show();
+					}
+				}
+			}
+
+			chooser.close();
+		});
+
+		dialog.show_all();
+
+		Self {
+			list,
+			pulse: pulse.clone(),
+			version: 0,
+			live
+		}
+	}
+
+
+	/**
+	 * Adds the events that have been recorded since the last update to the top of the list,
+	 * and removes the ones that have been dropped from the log.
+	 * Returns a boolean indicating if the window should continue to be open or not.
+	 */
+
+	pub fn update(&mut self) -> bool {
+		let pulse = self.pulse.borrow();
+		let log = &pulse.store().events;
+		if self.version == log.version { return *self.live.borrow(); }
+
+		// Clearing the log also changes its version, so fewer events may have been added than the version suggests.
+		let added = (log.version.wrapping_sub(self.version) as usize).min(log.events.len());
+		self.version = log.version;
+
+		for event in log.events.iter().skip(log.events.len() - added) {
+			self.list.insert_with_values(Some(0), &[ 0, 1, 2 ], &[ &event.timestamp(), &event.kind.label(), &event.description ]);
+		}
+
+		let excess = self.list.iter_n_children(None) - log.events.len() as i32;
+		for _ in 0 .. excess {
+			if let Some(iter) = self.list.iter_nth_child(None, log.events.len() as i32) { self.list.remove(&iter); }
+		}
+
+		*self.live.borrow()
+	}
+}
@@ -0,0 +1,193 @@
+/*!
+ * Contains the Hidden Streams window.
+ */
+
+use gtk::prelude::*;
+
+use crate::backend::{ Backend, StreamType };
+use crate::hidden::{ Filter, FilterKey, type_name, type_from_name };
+use crate::shared::Shared;
+
+/** The stream types a filter can apply to, and their labels. */
+const TYPE_CHOICES: [( Option<StreamType>, &str ); 5] = [
+	( None, "All Streams" ),
+	( Some(StreamType::SinkInput), "Playback" ),
+	( Some(StreamType::SourceOutput), "Recording" ),
+	( Some(StreamType::Sink), "Output Devices" ),
+	( Some(StreamType::Source), "Input Devices" )
+];
+
+
+/**
+ * The Hidden Streams popup window.
+ * Lists the filters that hide streams, and allows adding and removing them.
+ */
+
+pub struct HiddenStreams {
+	filters_box: gtk::Box,
+	pulse: Shared<dyn Backend>,
+
+	/** The version of the hide list that the list was last built from. */
+	version: Option<u32>,
+
+	/** Indicates if the popup should remain open. */
+	live: Shared<bool>
+}
+
+impl HiddenStreams {
+
+	/**
+	 * Creates the Hidden Streams window, and its contents.
+	 */
+
+	pub fn new(parent: &gtk::ApplicationWindow, pulse: &Shared<dyn Backend>) -> Self {
+		let dialog = gtk::Dialog::with_buttons(Some("Hidden Streams"), Some(parent), gtk::DialogFlags::all(), &[]);
+		dialog.set_border_width(0);
+
+		let live = Shared::new(true);
+		dialog.connect_response(|s, _| s.emit_close());
+		let live_clone = live.clone();
+		dialog.connect_close(move |_| { live_clone.replace(false); });
+
+		let geom = gdk::Geometry {
+			min_width: 550, min_height: 400,
+			max_width: 550, max_height: 10000,
+			base_width: -1, base_height: -1,
+			width_inc: -1, height_inc: -1,
+			min_aspect: 0.0, max_aspect: 0.0,
+			win_gravity: gdk::Gravity::Center
+		};
+
+		dialog.set_geometry_hints::<gtk::Dialog>(None, Some(&geom), gdk::WindowHints::MIN_SIZE | gdk::WindowHints::MAX_SIZE);
+		dialog.get_content_area().set_border_width(0);
+
+		let error_bar = gtk::InfoBar::new();
+		error_bar.set_message_type(gtk::MessageType::Error);
+		error_bar.set_show_close_button(true);
+		error_bar.set_no_show_all(true);
+		error_bar.connect_response(|bar, _| bar.hide());
+		let error_label = gtk::Label::new(None);
+		error_label.set_line_wrap(true);
+		error_label.show();
+		error_bar.get_content_area().add(&error_label);
+		dialog.get_content_area().pack_start(&error_bar, false, false, 0);
+
+		let add_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+		add_box.set_border_width(6);
+
+		let t = gtk::ComboBoxText::new();
+		for ( choice, label ) in &TYPE_CHOICES { t.append(Some(type_name(*choice)), label); }
+		t.set_active(Some(0));
+		add_box.pack_start(&t, false, false, 3);
+
+		let key = gtk::ComboBoxText::new();
+		for choice in &FilterKey::ALL { key.append(Some(choice.name()), choice.label()); }
+		key.set_active(Some(0));
+		add_box.pack_start(&key, false, false, 3);
+
+		let value = gtk::Entry::new();
+		value.set_placeholder_text(Some("Value"));
+		add_box.pack_start(&value, true, true, 3);
+
+		let add = gtk::Button::with_label("Hide");
+		add.get_style_context().add_class("suggested-action");
+		add_box.pack_start(&add, false, false, 3);
+
+		let pulse_clone = pulse.clone();
+		let value_clone = value.clone();
+		add.connect_clicked(move |_| {
+			let text = value_clone.get_text().trim().to_owned();
+			if text.is_empty() { return; }
+
+			let filter_t = t.get_active_id().and_then(|id| type_from_name(&id)).unwrap_or(None);
+			let filter_key = match key.get_active_id().and_then(|id| FilterKey::from_name(&id)) { Some(key) => key, None => return };
+
+			match Filter::new(filter_t, filter_key, &text) {
+				Ok(filter) => {
+					pulse_clone.borrow_mut().store_mut().hide_list.add(filter);
+					value_clone.set_text("");
+					error_bar.hide();
+				},
+				Err(err) => {
+					error_label.set_label(&format!("Invalid regular expression: {}", err));
+					error_bar.show();
+				}
+			}
+		});
+		value.connect_activate(move |_| add.clicked());
+
+		dialog.get_content_area().pack_start(&add_box, false, false, 0);
+		dialog.get_content_area().pack_start(&gtk::Separator::new(gtk::Orientation::Horizontal), false, false, 0);
+
+		let filters_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+
+		let scroller = gtk::ScrolledWindow::new::<gtk::Adjustment, gtk::Adjustment>(None, None);
+		scroller.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+		dialog.get_content_area().pack_start(&scroller, true, true, 0);
+		scroller.add(&filters_box);
+
+		dialog.show_all();
+
+		Self {
+			filters_box,
+			pulse: pulse.clone(),
+			version: None,
+			live
+		}
+	}
+
+
+	/**
+	 * Rebuilds the list of filters if they have changed,
+	 * returns a boolean indicating if the window should continue to be open or not.
+	 */
+
+	pub fn update(&mut self) -> bool {
+		let pulse = self.pulse.borrow();
+		let hide_list = &pulse.store().hide_list;
+		if self.version == Some(hide_list.version) { return *self.live.borrow(); }
+		self.version = Some(hide_list.version);
+
+		for child in self.filters_box.get_children() { self.filters_box.remove(&child); }
+
+		if hide_list.filters.is_empty() {
+			let label = gtk::Label::new(Some("No streams are hidden."));
+			label.get_style_context().add_class("dim-label");
+			self.filters_box.pack_start(&label, false, false, 24);
+		}
+
+		for (i, filter) in hide_list.filters.iter().enumerate() {
+			let row = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+			row.set_border_width(6);
+
+			let labels = gtk::Box::new(gtk::Orientation::Vertical, 0);
+			row.pack_start(&labels, true, true, 3);
+
+			let value = gtk::Label::new(Some(&filter.value));
+			value.set_halign(gtk::Align::Start);
+			value.set_ellipsize(pango::EllipsizeMode::End);
+			labels.pack_start(&value, false, false, 0);
+
+			let t = TYPE_CHOICES.iter().find(|( t, _ )| *t == filter.t).map_or("", |( _, label )| label);
+			let detail = gtk::Label::new(Some(&format!("{} · {}", filter.key.label(), t)));
+			detail.set_halign(gtk::Align::Start);
+			detail.set_ellipsize(pango::EllipsizeMode::End);
+			detail.get_style_context().add_class("dim-label");
+			labels.pack_start(&detail, false, false, 0);
+
+			let remove = gtk::Button::from_icon_name(Some("user-trash-symbolic"), gtk::IconSize::Button);
+			remove.set_tooltip_text(Some("Show"));
+			remove.set_valign(gtk::Align::Center);
+			remove.get_style_context().add_class("flat");
+			let pulse = self.pulse.clone();
+			remove.connect_clicked(move |_| pulse.borrow_mut().store_mut().hide_list.remove(i));
+			row.pack_end(&remove, false, false, 3);
+
+			self.filters_box.pack_start(&row, false, false, 0);
+			self.filters_box.pack_start(&gtk::Separator::new(gtk::Orientation::Horizontal), false, false, 0);
+		}
+
+		self.filters_box.show_all();
+		*self.live.borrow()
+	}
+}
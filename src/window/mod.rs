@@ -12,3 +12,30 @@ pub use about::*;
 
 mod profiles;
 pub use profiles::*;
+
+mod modules;
+pub use modules::*;
+
+mod virtual_device;
+pub use virtual_device::*;
+
+mod speaker_test;
+pub use speaker_test::*;
+
+mod remembered;
+pub use remembered::*;
+
+mod hidden_streams;
+pub use hidden_streams::*;
+
+mod server_info;
+pub use server_info::*;
+
+mod connect_server;
+pub use connect_server::*;
+
+mod event_log;
+pub use event_log::*;
+
+mod ducking;
+pub use ducking::*;
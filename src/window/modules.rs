@@ -0,0 +1,177 @@
+/*!
+ * Contains the Modules window.
+ */
+
+use gtk::prelude::*;
+
+use crate::backend::{ Backend, ModuleData };
+use crate::shared::Shared;
+
+
+/**
+ * The Modules popup window.
+ * Lists the modules loaded into the server, and allows loading and unloading them.
+ */
+
+pub struct Modules {
+	modules_box: gtk::Box,
+	error_bar: gtk::InfoBar,
+	error_label: gtk::Label,
+	pulse: Shared<dyn Backend>,
+
+	/** The modules that the list was last built from, sorted by index. */
+	modules: Option<Vec<ModuleData>>,
+
+	/** The number of server errors that have been seen, so that only new ones are shown. */
+	errors: usize,
+
+	/** Indicates if the popup should remain open. */
+	live: Shared<bool>
+}
+
+impl Modules {
+
+	/**
+	 * Creates the Modules window, and its contents.
+	 */
+
+	pub fn new(parent: &gtk::ApplicationWindow, pulse: &Shared<dyn Backend>) -> Self {
+		let dialog = gtk::Dialog::with_buttons(Some("Modules"), Some(parent), gtk::DialogFlags::all(), &[]);
+		dialog.set_border_width(0);
+
+		let live = Shared::new(true);
+		dialog.connect_response(|s, _| s.emit_close());
+		let live_clone = live.clone();
+		dialog.connect_close(move |_| { live_clone.replace(false); });
+
+		let geom = gdk::Geometry {
+			min_width: 550, min_height: 550,
+			max_width: 550, max_height: 10000,
+			base_width: -1, base_height: -1,
+			width_inc: -1, height_inc: -1,
+			min_aspect: 0.0, max_aspect: 0.0,
+			win_gravity: gdk::Gravity::Center
+		};
+
+		dialog.set_geometry_hints::<gtk::Dialog>(None, Some(&geom), gdk::WindowHints::MIN_SIZE | gdk::WindowHints::MAX_SIZE);
+		dialog.get_content_area().set_border_width(0);
+
+		let error_bar = gtk::InfoBar::new();
+		error_bar.set_message_type(gtk::MessageType::Error);
+		error_bar.set_show_close_button(true);
+		error_bar.set_no_show_all(true);
+		error_bar.connect_response(|bar, _| bar.hide());
+		let error_label = gtk::Label::new(None);
+		error_label.set_line_wrap(true);
+		error_label.show();
+		error_bar.get_content_area().add(&error_label);
+		dialog.get_content_area().pack_start(&error_bar, false, false, 0);
+
+		let load_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+		load_box.set_border_width(6);
+
+		let name = gtk::Entry::new();
+		name.set_placeholder_text(Some("Module"));
+		load_box.pack_start(&name, false, false, 3);
+
+		let argument = gtk::Entry::new();
+		argument.set_placeholder_text(Some("Arguments"));
+		load_box.pack_start(&argument, true, true, 3);
+
+		let load = gtk::Button::with_label("Load");
+		load.get_style_context().add_class("suggested-action");
+		load_box.pack_start(&load, false, false, 3);
+
+		let pulse_clone = pulse.clone();
+		let name_clone = name.clone();
+		load.connect_clicked(move |_| {
+			let module = name_clone.get_text().trim().to_owned();
+			if module.is_empty() { return; }
+			pulse_clone.borrow().load_module(&module, argument.get_text().trim());
+		});
+		name.connect_activate(move |_| load.clicked());
+
+		dialog.get_content_area().pack_start(&load_box, false, false, 0);
+		dialog.get_content_area().pack_start(&gtk::Separator::new(gtk::Orientation::Horizontal), false, false, 0);
+
+		let modules_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+
+		let scroller = gtk::ScrolledWindow::new::<gtk::Adjustment, gtk::Adjustment>(None, None);
+		scroller.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+		dialog.get_content_area().pack_start(&scroller, true, true, 0);
+		scroller.add(&modules_box);
+
+		dialog.show_all();
+
+		Self {
+			modules_box,
+			error_bar,
+			error_label,
+			errors: pulse.borrow().store().errors.len(),
+			pulse: pulse.clone(),
+			modules: None,
+			live
+		}
+	}
+
+
+	/**
+	 * Rebuilds the list of modules if they have changed, and shows the latest error if there is a new one.
+	 * Returns a boolean indicating if the window should continue to be open or not.
+	 */
+
+	pub fn update(&mut self) -> bool {
+		let pulse = self.pulse.borrow();
+		let store = pulse.store();
+
+		if store.errors.len() > self.errors {
+			self.errors = store.errors.len();
+			self.error_label.set_label(store.errors.last().unwrap());
+			self.error_bar.show();
+		}
+
+		let mut modules: Vec<ModuleData> = store.modules.values().cloned().collect();
+		modules.sort_by_key(|m| m.index);
+		if self.modules.as_ref() == Some(&modules) { return *self.live.borrow(); }
+
+		for child in self.modules_box.get_children() { self.modules_box.remove(&child); }
+
+		for module in &modules {
+			let row = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+			row.set_border_width(6);
+
+			let labels = gtk::Box::new(gtk::Orientation::Vertical, 0);
+			row.pack_start(&labels, true, true, 3);
+
+			let name = gtk::Label::new(Some(&module.name));
+			name.set_halign(gtk::Align::Start);
+			name.set_ellipsize(pango::EllipsizeMode::End);
+			labels.pack_start(&name, false, false, 0);
+
+			if !module.argument.is_empty() {
+				let argument = gtk::Label::new(Some(&module.argument));
+				argument.set_halign(gtk::Align::Start);
+				argument.set_ellipsize(pango::EllipsizeMode::End);
+				argument.set_tooltip_text(Some(&module.argument));
+				argument.get_style_context().add_class("dim-label");
+				labels.pack_start(&argument, false, false, 0);
+			}
+
+			let unload = gtk::Button::from_icon_name(Some("user-trash-symbolic"), gtk::IconSize::Button);
+			unload.set_tooltip_text(Some("Unload"));
+			unload.set_valign(gtk::Align::Center);
+			unload.get_style_context().add_class("flat");
+			let index = module.index;
+			let pulse = self.pulse.clone();
+			unload.connect_clicked(move |_| pulse.borrow().unload_module(index));
+			row.pack_end(&unload, false, false, 3);
+
+			self.modules_box.pack_start(&row, false, false, 0);
+			self.modules_box.pack_start(&gtk::Separator::new(gtk::Orientation::Horizontal), false, false, 0);
+		}
+
+		self.modules = Some(modules);
+		self.modules_box.show_all();
+		*self.live.borrow()
+	}
+}
@@ -2,16 +2,17 @@
  * Contains he main application window, and associated data structs.
  */
 
-use std::collections::HashMap;
+use std::collections::{ HashMap, HashSet };
 
 use gtk::prelude::*;
 use gio::prelude::*;
 
 use super::style;
-use crate::pulse::Pulse;
 use crate::shared::Shared;
-use super::{ about, Profiles };
-use crate::meter::{ Meter, SinkMeter, SourceMeter, StreamMeter };
+use crate::backend::{ Backend, ConnectionState, StreamType, StreamData, is_own_stream };
+use super::{ about, ConnectServer, Ducking, EventLog, Profiles, Modules, Remembered, HiddenStreams, ServerInfo, SpeakerTest, VirtualDevice };
+use crate::meter::{ Meter, SinkMeter, SourceMeter, StreamMeter, GroupMeter, VolumeCurve, ScaleOptions, group_key };
+use crate::meter::{ MAX_NATURAL_VOL, MAX_VOLUME_CHOICES, STEP_CHOICES };
 
 
 /**
@@ -29,9 +30,23 @@ pub struct Meters {
 	pub source_outputs: HashMap<u32, StreamMeter>,
 	pub source_outputs_box: gtk::Box,
 
+	/** The meters of applications with more than one stream, keyed by `group_key`. */
+	pub sink_input_groups: HashMap<String, GroupMeter>,
+	pub source_output_groups: HashMap<String, GroupMeter>,
+
 	pub show_visualizers: bool,
 	pub separate_channels: bool,
 	pub remember_position: bool,
+	pub show_monitors: bool,
+	pub show_hidden: bool,
+	pub group_streams: bool,
+	pub volume_curve: VolumeCurve,
+	pub max_volume: u32,
+	pub volume_step: u32,
+	pub device_max_volumes: HashMap<String, u32>,
+
+	/** The server to connect to, or None for the default server. */
+	pub server: Option<String>,
 
 	pub window_position: (i32, i32),
 
@@ -47,7 +62,7 @@ impl Meters {
 	 * * `pulse` - The Pulse instance used by the app.
 	 */
 
-	pub fn new(pulse: &Shared<Pulse>) -> Self {
+	pub fn new(pulse: &Shared<dyn Backend>) -> Self {
 		let sink = SinkMeter::new(pulse.clone());
 
 		let sink_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
@@ -81,9 +96,19 @@ impl Meters {
 			sink_inputs_box, source_outputs_box,
 			sink_inputs: HashMap::new(),
 			source_outputs: HashMap::new(),
+			sink_input_groups: HashMap::new(),
+			source_output_groups: HashMap::new(),
 			show_visualizers: true,
 			separate_channels: false,
 			remember_position: false,
+			show_monitors: false,
+			show_hidden: false,
+			group_streams: false,
+			volume_curve: VolumeCurve::default(),
+			max_volume: 150,
+			volume_step: 5,
+			device_max_volumes: HashMap::new(),
+			server: None,
 
 			window_position: (0, 0),
 
@@ -104,11 +129,18 @@ impl Meters {
 	pub fn save_config(&self) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
 		let config_path = self._config_path.clone();
 
-		let config_content = format!(
-			"show_visualizers={}\nseparate_channels={}\nremember_position={}\nwindow_position_x={}\nwindow_position_y={}\n",
-			self.show_visualizers, self.separate_channels, self.remember_position, self.window_position.0, self.window_position.1
+		let mut config_content = format!(
+			"show_visualizers={}\nseparate_channels={}\nremember_position={}\nshow_monitors={}\nshow_hidden={}\ngroup_streams={}\nvolume_curve={}\nmax_volume={}\nvolume_step={}\nwindow_position_x={}\nwindow_position_y={}\n",
+			self.show_visualizers, self.separate_channels, self.remember_position, self.show_monitors, self.show_hidden, self.group_streams, self.volume_curve.name(),
+			self.max_volume, self.volume_step, self.window_position.0, self.window_position.1
 		);
 
+		for (device, max_volume) in &self.device_max_volumes {
+			config_content.push_str(&format!("device_max_volume:{}={}\n", device, max_volume));
+		}
+
+		if let Some(server) = &self.server { config_content.push_str(&format!("server={}\n", server)); }
+
 		let dir_name = std::path::Path::new(&config_path).parent().ok_or("incorrect directory")?;
 		std::fs::create_dir_all(dir_name)?;
 		std::fs::write(&config_path, config_content)?;
@@ -135,8 +167,20 @@ impl Meters {
 				"show_visualizers" => self.show_visualizers = value.parse().unwrap_or(self.show_visualizers),
 				"separate_channels" => self.separate_channels = value.parse().unwrap_or(self.separate_channels),
 				"remember_position" => self.remember_position = value.parse().unwrap_or(self.remember_position),
+				"show_monitors" => self.show_monitors = value.parse().unwrap_or(self.show_monitors),
+				"show_hidden" => self.show_hidden = value.parse().unwrap_or(self.show_hidden),
+				"group_streams" => self.group_streams = value.parse().unwrap_or(self.group_streams),
+				"volume_curve" => self.volume_curve = VolumeCurve::from_name(value).unwrap_or(self.volume_curve),
+				"max_volume" => self.max_volume = value.parse().ok().filter(|v| *v > 0).unwrap_or(self.max_volume),
+				"volume_step" => self.volume_step = value.parse().ok().filter(|v| *v > 0).unwrap_or(self.volume_step),
+				_ if key.starts_with("device_max_volume:") => {
+					if let Some(max_volume) = value.parse().ok().filter(|v: &u32| *v > 0) {
+						self.device_max_volumes.insert(key["device_max_volume:".len() ..].to_owned(), max_volume);
+					}
+				},
 				"window_position_x" => self.window_position.0 = value.parse().unwrap_or(self.window_position.0),
 				"window_position_y" => self.window_position.1 = value.parse().unwrap_or(self.window_position.1),
+				"server" => self.server = Some(value.to_owned()).filter(|server| !server.is_empty()),
 				_ => {}
 			}
 		}
@@ -167,6 +211,80 @@ impl Meters {
 	}
 
 
+	/**
+	 * Sets the curve used by the volume scales, and returns it.
+	 *
+	 * * `curve` - The curve to use.
+	 */
+
+	fn set_volume_curve(&mut self, curve: VolumeCurve) -> VolumeCurve {
+		self.volume_curve = curve;
+		let _ = self.save_config();
+		self.volume_curve
+	}
+
+
+	/**
+	 * Sets the default maximum volume of the scales, and returns it.
+	 *
+	 * * `max_volume` - The maximum volume, as a percentage.
+	 */
+
+	fn set_max_volume(&mut self, max_volume: u32) -> u32 {
+		self.max_volume = max_volume;
+		let _ = self.save_config();
+		self.max_volume
+	}
+
+
+	/**
+	 * Sets the step size of the scales, and returns it.
+	 *
+	 * * `step` - The step size, as a percentage.
+	 */
+
+	fn set_volume_step(&mut self, step: u32) -> u32 {
+		self.volume_step = step;
+		let _ = self.save_config();
+		self.volume_step
+	}
+
+
+	/**
+	 * Sets or clears the maximum volume of a device, which overrides the default maximum volume.
+	 *
+	 * * `device`     - The name of the sink or source.
+	 * * `max_volume` - The maximum volume as a percentage, or None to use the default.
+	 */
+
+	fn set_device_max_volume(&mut self, device: &str, max_volume: Option<u32>) {
+		match max_volume {
+			Some(max_volume) => self.device_max_volumes.insert(device.to_owned(), max_volume),
+			None => self.device_max_volumes.remove(device)
+		};
+		let _ = self.save_config();
+	}
+
+
+	/**
+	 * Gets the scale options for a meter.
+	 *
+	 * * `device` - The name of the meter's sink or source, or None if the meter isn't for a device.
+	 */
+
+	pub fn scale_options(&self, device: Option<&str>) -> ScaleOptions {
+		let device_max = device.and_then(|device| self.device_max_volumes.get(device)).copied();
+		let percent = |percent: u32| (MAX_NATURAL_VOL as f64 * percent as f64 / 100.0).round();
+
+		ScaleOptions {
+			curve: self.volume_curve,
+			max_volume: percent(device_max.unwrap_or(self.max_volume)) as u32,
+			step: percent(self.volume_step),
+			device_max: device_max.is_some()
+		}
+	}
+
+
 	/**
 	 * Toggles the remember position setting, and returns its current state.
 	 */
@@ -176,6 +294,104 @@ impl Meters {
 		let _ = self.save_config();
 		self.remember_position
 	}
+
+
+	/**
+	 * Toggles the show monitor sources setting, and returns its current state.
+	 */
+
+	fn toggle_show_monitors(&mut self) -> bool {
+		self.show_monitors = !self.show_monitors;
+		let _ = self.save_config();
+		self.show_monitors
+	}
+
+
+	/**
+	 * Toggles the show hidden streams setting, and returns its current state.
+	 */
+
+	fn toggle_show_hidden(&mut self) -> bool {
+		self.show_hidden = !self.show_hidden;
+		let _ = self.save_config();
+		self.show_hidden
+	}
+
+
+	/**
+	 * Toggles the group streams by application setting, and returns its current state.
+	 */
+
+	fn toggle_group_streams(&mut self) -> bool {
+		self.group_streams = !self.group_streams;
+		let _ = self.save_config();
+		self.group_streams
+	}
+
+
+	/**
+	 * Groups the meters of streams that belong to the same application, if grouping is enabled.
+	 * Applications with more than one stream get a group meter, and their streams' meters are moved into it.
+	 * The meters of other streams are kept in the streams box, and groups that are no longer needed are removed.
+	 *
+	 * * `pulse`   - The Pulse instance used by the app.
+	 * * `t`       - The type of the streams, either sink inputs or source outputs.
+	 * * `streams` - The streams in the store.
+	 */
+
+	fn update_groups(&mut self, pulse: &Shared<dyn Backend>, t: StreamType, streams: &HashMap<u32, StreamData>) {
+		let options = self.scale_options(None);
+		let show = self.show_visualizers;
+
+		let mut members: HashMap<String, Vec<&StreamData>> = HashMap::new();
+		if self.group_streams {
+			for stream in streams.values() {
+				if let Some(key) = group_key(stream) { members.entry(key).or_default().push(stream); }
+			}
+		}
+		members.retain(|_, streams| streams.len() > 1);
+
+		let ( groups, meters, container ) = if t == StreamType::SinkInput {
+			( &mut self.sink_input_groups, &mut self.sink_inputs, &self.sink_inputs_box ) }
+			else { ( &mut self.source_output_groups, &mut self.source_outputs, &self.source_outputs_box ) };
+
+		for ( key, streams ) in &members {
+			let group = groups.entry(key.clone()).or_insert_with(|| GroupMeter::new(pulse.clone(), t));
+			if group.widget.get_parent().is_none() { container.pack_start(&group.widget, false, false, 0); }
+
+			group.set_scale_options(options);
+			group.set_members(streams);
+			group.set_peak(if show { Some(streams.iter().flat_map(|stream| stream.peak.iter().copied()).collect()) } else { None });
+		}
+
+		// Meters are moved out of the groups that are removed, as removing a group destroys the meters inside it.
+		for ( index, meter ) in meters.iter() {
+			let target = streams.get(index).and_then(group_key).filter(|key| members.contains_key(key)).and_then(|key| groups.get(&key))
+				.map_or(container, |group| &group.members_box);
+
+			if meter.widget.get_parent().as_ref() == Some(target.upcast_ref()) { continue; }
+			if let Some(parent) = meter.widget.get_parent() { parent.downcast::<gtk::Container>().unwrap().remove(&meter.widget); }
+			target.pack_start(&meter.widget, false, false, 0);
+		}
+
+		groups.retain(|key, group| {
+			let keep = members.contains_key(key);
+			if !keep { container.remove(&group.widget); }
+			keep
+		});
+	}
+
+
+	/**
+	 * Sets the server to connect to on startup.
+	 *
+	 * * `server` - The server string, or None for the default server.
+	 */
+
+	fn set_server(&mut self, server: Option<String>) {
+		self.server = server;
+		let _ = self.save_config();
+	}
 }
 
 
@@ -186,11 +402,24 @@ impl Meters {
 
 pub struct Myxer {
 	window: gtk::ApplicationWindow,
+	content: gtk::Stack,
+	stack: gtk::Stack,
+	switcher: gtk::StackSwitcher,
 
-	pulse: Shared<Pulse>,
+	pulse: Shared<dyn Backend>,
 	meters: Shared<Meters>,
-
-	profiles: Shared<Option<Profiles>>
+	state: ConnectionState,
+
+	profiles: Shared<Option<Profiles>>,
+	modules: Shared<Option<Modules>>,
+	virtual_device: Shared<Option<VirtualDevice>>,
+	speaker_test: Shared<Option<SpeakerTest>>,
+	remembered: Shared<Option<Remembered>>,
+	hidden_streams: Shared<Option<HiddenStreams>>,
+	server_info: Shared<Option<ServerInfo>>,
+	connect_server: Shared<Option<ConnectServer>>,
+	event_log: Shared<Option<EventLog>>,
+	ducking: Shared<Option<Ducking>>
 }
 
 impl Myxer {
@@ -200,13 +429,20 @@ impl Myxer {
 	 *
 	 * * `app` - The GTK application.
 	 * * `pulse` - The Pulse store instance.
+	 * * `server` - The server to connect to, overriding the configured server, as passed on the command line.
 	 */
 
-	pub fn new(app: &gtk::Application, pulse: &Shared<Pulse>) -> Self {
+	pub fn new(app: &gtk::Application, pulse: &Shared<dyn Backend>, server: Option<String>) -> Self {
 		let window = gtk::ApplicationWindow::new(app);
 		let header = gtk::HeaderBar::new();
 		let stack = gtk::Stack::new();
+		let content = gtk::Stack::new();
+		let stack_switcher = gtk::StackSwitcher::new();
 		let meters = Shared::new(Meters::new(pulse));
+		pulse.borrow_mut().set_show_monitors(meters.borrow().show_monitors);
+		pulse.borrow_mut().set_show_hidden(meters.borrow().show_hidden);
+		pulse.borrow_mut().set_server(server.or_else(|| meters.borrow().server.clone()));
+		let server_label = gtk::Label::new(pulse.borrow().server());
 
 		{
 			window.set_title("Volume Mixer");
@@ -226,7 +462,6 @@ impl Myxer {
 			window.get_style_context().add_class("Myxer");
 			style::style(&window);
 
-			let stack_switcher = gtk::StackSwitcher::new();
 			stack_switcher.set_stack(Some(&stack));
 
 			header.set_show_close_button(true);
@@ -244,6 +479,14 @@ impl Myxer {
 			title.get_style_context().add_class("title");
 			title_hor.pack_start(&title, true, true, 0);
 
+			server_label.get_style_context().add_class("subtitle");
+			server_label.get_style_context().add_class("dim-label");
+			server_label.set_ellipsize(pango::EllipsizeMode::Middle);
+			server_label.set_max_width_chars(24);
+			server_label.set_no_show_all(true);
+			server_label.set_visible(pulse.borrow().server().is_some());
+			title_vert.pack_start(&server_label, true, true, 0);
+
 			window.set_titlebar(Some(&header));
 
 			if meters.borrow().remember_position {
@@ -281,11 +524,73 @@ impl Myxer {
 			remember_position.set_action_name(Some("app.remember_position"));
 			prefs_box.add(&remember_position);
 
+			let show_monitors = gtk::ModelButton::new();
+			show_monitors.set_property_text(Some("Show Monitor Sources"));
+			show_monitors.set_action_name(Some("app.show_monitors"));
+			prefs_box.add(&show_monitors);
+
+			let show_hidden = gtk::ModelButton::new();
+			show_hidden.set_property_text(Some("Show Hidden Streams"));
+			show_hidden.set_action_name(Some("app.show_hidden"));
+			prefs_box.add(&show_hidden);
+
+			let group_streams = gtk::ModelButton::new();
+			group_streams.set_property_text(Some("Group by Application"));
+			group_streams.set_action_name(Some("app.group_streams"));
+			prefs_box.add(&group_streams);
+
+			prefs_box.pack_start(&gtk::Separator::new(gtk::Orientation::Horizontal), false, false, 4);
+
+			let volume_scale = gtk::ModelButton::new();
+			volume_scale.set_property_text(Some("Volume Scale"));
+			volume_scale.set_property_menu_name(Some("volume_scale"));
+			prefs_box.add(&volume_scale);
+
+			let new_virtual_device = gtk::ModelButton::new();
+			new_virtual_device.set_property_text(Some("New Virtual Device..."));
+			new_virtual_device.set_action_name(Some("app.new_virtual_device"));
+			prefs_box.add(&new_virtual_device);
+
 			let card_profiles = gtk::ModelButton::new();
 			card_profiles.set_property_text(Some("Card Profiles..."));
 			card_profiles.set_action_name(Some("app.card_profiles"));
 			prefs_box.add(&card_profiles);
 
+			let connect_server = gtk::ModelButton::new();
+			connect_server.set_property_text(Some("Connect to Server..."));
+			connect_server.set_action_name(Some("app.connect_server"));
+			prefs_box.add(&connect_server);
+
+			let modules = gtk::ModelButton::new();
+			modules.set_property_text(Some("Modules..."));
+			modules.set_action_name(Some("app.modules"));
+			prefs_box.add(&modules);
+
+			let remembered_volumes = gtk::ModelButton::new();
+			remembered_volumes.set_property_text(Some("Remembered Volumes..."));
+			remembered_volumes.set_action_name(Some("app.remembered_volumes"));
+			prefs_box.add(&remembered_volumes);
+
+			let hidden_streams = gtk::ModelButton::new();
+			hidden_streams.set_property_text(Some("Hidden Streams..."));
+			hidden_streams.set_action_name(Some("app.hidden_streams"));
+			prefs_box.add(&hidden_streams);
+
+			let ducking = gtk::ModelButton::new();
+			ducking.set_property_text(Some("Ducking..."));
+			ducking.set_action_name(Some("app.ducking"));
+			prefs_box.add(&ducking);
+
+			let server_info = gtk::ModelButton::new();
+			server_info.set_property_text(Some("Server Info..."));
+			server_info.set_action_name(Some("app.server_info"));
+			prefs_box.add(&server_info);
+
+			let event_log = gtk::ModelButton::new();
+			event_log.set_property_text(Some("Event Log..."));
+			event_log.set_action_name(Some("app.event_log"));
+			prefs_box.add(&event_log);
+
 			prefs_box.pack_start(&gtk::Separator::new(gtk::Orientation::Horizontal), false, false, 4);
 
 			let about = gtk::ModelButton::new();
@@ -293,7 +598,42 @@ impl Myxer {
 			about.set_action_name(Some("app.about"));
 			prefs_box.add(&about);
 
+			let scale_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+			prefs.add(&scale_box);
+			prefs.set_child_submenu(&scale_box, Some("volume_scale"));
+
+			let back = gtk::ModelButton::new();
+			back.set_property_text(Some("Volume Scale"));
+			back.set_property_menu_name(Some("main"));
+			back.set_property_inverted(true);
+			back.set_property_centered(true);
+			scale_box.add(&back);
+
+			let sections = [
+				( "Curve", "app.volume_curve", [ ( VolumeCurve::Cubic, "Cubic" ), ( VolumeCurve::Linear, "Linear" ), ( VolumeCurve::Decibel, "Decibels" ) ]
+					.iter().map(|( curve, label )| ( curve.name().to_variant(), label.to_string() )).collect::<Vec<_>>() ),
+				( "Maximum Volume", "app.max_volume", MAX_VOLUME_CHOICES.iter().map(|v| ( v.to_variant(), format!("{}%", v) )).collect() ),
+				( "Step Size", "app.volume_step", STEP_CHOICES.iter().map(|v| ( v.to_variant(), format!("{}%", v) )).collect() )
+			];
+
+			for ( title, action, choices ) in &sections {
+				scale_box.pack_start(&gtk::Separator::new(gtk::Orientation::Horizontal), false, false, 4);
+
+				let label = gtk::Label::new(Some(title));
+				label.set_sensitive(false);
+				scale_box.pack_start(&label, true, true, 3);
+
+				for ( value, label ) in choices {
+					let button = gtk::ModelButton::new();
+					button.set_property_text(Some(label));
+					button.set_action_name(Some(action));
+					button.set_action_target_value(Some(value));
+					scale_box.add(&button);
+				}
+			}
+
 			prefs_box.show_all();
+			scale_box.show_all();
 			prefs_button.connect_clicked(move |_| prefs.popup());
 		}
 
@@ -325,11 +665,38 @@ impl Myxer {
 			stack.add_titled(&output, "output", "Output");
 			stack.add_titled(&input, "input", "Input");
 
-			window.add(&stack);
+			let reconnecting = gtk::Box::new(gtk::Orientation::Vertical, 0);
+			reconnecting.set_valign(gtk::Align::Center);
+
+			let spinner = gtk::Spinner::new();
+			spinner.start();
+			reconnecting.pack_start(&spinner, false, false, 6);
+
+			let label = gtk::Label::new(Some("Reconnecting…"));
+			label.get_style_context().add_class("title");
+			reconnecting.pack_start(&label, false, false, 3);
+
+			let sublabel = gtk::Label::new(Some("Waiting for the PulseAudio server."));
+			sublabel.set_sensitive(false);
+			reconnecting.pack_start(&sublabel, false, false, 0);
+
+			content.add_named(&stack, "meters");
+			content.add_named(&reconnecting, "reconnecting");
+
+			window.add(&content);
 			window.show_all();
 		}
 
 		let profiles = Shared::new(None);
+		let modules = Shared::new(None);
+		let virtual_device = Shared::new(None);
+		let speaker_test = Shared::new(None);
+		let remembered = Shared::new(None);
+		let hidden_streams = Shared::new(None);
+		let server_info = Shared::new(None);
+		let connect_server = Shared::new(None);
+		let event_log = Shared::new(None);
+		let ducking = Shared::new(None);
 
 		{
 			let window = window.clone();
@@ -342,13 +709,113 @@ impl Myxer {
 			actions.add_action(&about);
 
 			let card_profiles = gio::SimpleAction::new("card_profiles", None);
-			let pulse = pulse.clone();
+			let window_clone = window.clone();
+			let pulse_clone = pulse.clone();
 			let profiles = profiles.clone();
 			card_profiles.connect_activate(move |_, _| {
-				profiles.replace(Some(Profiles::new(&window, &pulse)));
+				profiles.replace(Some(Profiles::new(&window_clone, &pulse_clone)));
 			});
 			actions.add_action(&card_profiles);
 
+			let modules_action = gio::SimpleAction::new("modules", None);
+			let window_clone = window.clone();
+			let pulse_clone = pulse.clone();
+			let modules = modules.clone();
+			modules_action.connect_activate(move |_, _| {
+				modules.replace(Some(Modules::new(&window_clone, &pulse_clone)));
+			});
+			actions.add_action(&modules_action);
+
+			let new_virtual_device = gio::SimpleAction::new("new_virtual_device", None);
+			let window_clone = window.clone();
+			let pulse_clone = pulse.clone();
+			let virtual_device = virtual_device.clone();
+			new_virtual_device.connect_activate(move |_, _| {
+				virtual_device.replace(Some(VirtualDevice::new(&window_clone, &pulse_clone)));
+			});
+			actions.add_action(&new_virtual_device);
+
+			// The parameter is the index of the sink to test.
+			let speaker_test_action = gio::SimpleAction::new("speaker_test", glib::VariantTy::new("u").ok());
+			let window_clone = window.clone();
+			let pulse_clone = pulse.clone();
+			let speaker_test = speaker_test.clone();
+			speaker_test_action.connect_activate(move |_, value| {
+				if let Some(sink) = value.and_then(|v| v.get::<u32>()) {
+					speaker_test.replace(Some(SpeakerTest::new(&window_clone, &pulse_clone, sink)));
+				}
+			});
+			actions.add_action(&speaker_test_action);
+
+			let hidden_streams_action = gio::SimpleAction::new("hidden_streams", None);
+			let window_clone = window.clone();
+			let pulse_clone = pulse.clone();
+			let hidden_streams = hidden_streams.clone();
+			hidden_streams_action.connect_activate(move |_, _| {
+				hidden_streams.replace(Some(HiddenStreams::new(&window_clone, &pulse_clone)));
+			});
+			actions.add_action(&hidden_streams_action);
+
+			let server_info_action = gio::SimpleAction::new("server_info", None);
+			let window_clone = window.clone();
+			let pulse_clone = pulse.clone();
+			let server_info = server_info.clone();
+			server_info_action.connect_activate(move |_, _| {
+				server_info.replace(Some(ServerInfo::new(&window_clone, &pulse_clone)));
+			});
+			actions.add_action(&server_info_action);
+
+			let event_log_action = gio::SimpleAction::new("event_log", None);
+			let window_clone = window.clone();
+			let pulse_clone = pulse.clone();
+			let event_log = event_log.clone();
+			event_log_action.connect_activate(move |_, _| {
+				event_log.replace(Some(EventLog::new(&window_clone, &pulse_clone)));
+			});
+			actions.add_action(&event_log_action);
+
+			let ducking_action = gio::SimpleAction::new("ducking", None);
+			let window_clone = window.clone();
+			let pulse_clone = pulse.clone();
+			let ducking = ducking.clone();
+			ducking_action.connect_activate(move |_, _| {
+				ducking.replace(Some(Ducking::new(&window_clone, &pulse_clone)));
+			});
+			actions.add_action(&ducking_action);
+
+			let connect_server_action = gio::SimpleAction::new("connect_server", None);
+			let window_clone = window.clone();
+			let pulse_clone = pulse.clone();
+			let connect_server = connect_server.clone();
+			connect_server_action.connect_activate(move |_, _| {
+				connect_server.replace(Some(ConnectServer::new(&window_clone, &pulse_clone)));
+			});
+			actions.add_action(&connect_server_action);
+
+			// The parameter is the server string, which is empty for the default server.
+			let set_server = gio::SimpleAction::new("set_server", glib::VariantTy::new("s").ok());
+			let meters_clone = meters.clone();
+			let pulse_clone = pulse.clone();
+			set_server.connect_activate(move |_, value| {
+				if let Some(server) = value.and_then(|v| v.get::<String>()) {
+					let server = Some(server).filter(|server| !server.is_empty());
+					pulse_clone.borrow_mut().set_server(server.clone());
+					meters_clone.borrow_mut().set_server(server.clone());
+
+					server_label.set_text(server.as_deref().unwrap_or(""));
+					server_label.set_visible(server.is_some());
+				}
+			});
+			actions.add_action(&set_server);
+
+			let remembered_volumes = gio::SimpleAction::new("remembered_volumes", None);
+			let pulse_clone = pulse.clone();
+			let remembered = remembered.clone();
+			remembered_volumes.connect_activate(move |_, _| {
+				remembered.replace(Some(Remembered::new(&window, &pulse_clone)));
+			});
+			actions.add_action(&remembered_volumes);
+
 			let meters_clone = meters.clone();
 			let split_channels = gio::SimpleAction::new_stateful("split_channels", glib::VariantTy::new("bool").ok(), &(meters_clone.borrow().separate_channels).to_variant());
 			split_channels.connect_activate(move |s, _| s.set_state(&meters_clone.borrow_mut().toggle_separate_channels().to_variant()));
@@ -359,24 +826,150 @@ impl Myxer {
 			remember_position.connect_activate(move |s, _| s.set_state(&meters_clone.borrow_mut().toggle_remember_position().to_variant()));
 			actions.add_action(&remember_position);
 
+			let meters_clone = meters.clone();
+			let pulse_clone = pulse.clone();
+			let show_monitors = gio::SimpleAction::new_stateful("show_monitors", glib::VariantTy::new("bool").ok(), &(meters_clone.borrow().show_monitors).to_variant());
+			show_monitors.connect_activate(move |s, _| {
+				let show = meters_clone.borrow_mut().toggle_show_monitors();
+				pulse_clone.borrow_mut().set_show_monitors(show);
+				s.set_state(&show.to_variant());
+			});
+			actions.add_action(&show_monitors);
+
+			let meters_clone = meters.clone();
+			let pulse_clone = pulse.clone();
+			let show_hidden = gio::SimpleAction::new_stateful("show_hidden", glib::VariantTy::new("bool").ok(), &(meters_clone.borrow().show_hidden).to_variant());
+			show_hidden.connect_activate(move |s, _| {
+				let show = meters_clone.borrow_mut().toggle_show_hidden();
+				pulse_clone.borrow_mut().set_show_hidden(show);
+				s.set_state(&show.to_variant());
+			});
+			actions.add_action(&show_hidden);
+
+			let meters_clone = meters.clone();
+			let group_streams = gio::SimpleAction::new_stateful("group_streams", glib::VariantTy::new("bool").ok(), &(meters_clone.borrow().group_streams).to_variant());
+			group_streams.connect_activate(move |s, _| s.set_state(&meters_clone.borrow_mut().toggle_group_streams().to_variant()));
+			actions.add_action(&group_streams);
+
+			let meters_clone = meters.clone();
+			let volume_curve = gio::SimpleAction::new_stateful("volume_curve", glib::VariantTy::new("s").ok(), &(meters_clone.borrow().volume_curve.name()).to_variant());
+			volume_curve.connect_activate(move |s, value| {
+				if let Some(curve) = value.and_then(|v| v.get::<String>()).and_then(|name| VolumeCurve::from_name(&name)) {
+					s.set_state(&meters_clone.borrow_mut().set_volume_curve(curve).name().to_variant());
+				}
+			});
+			actions.add_action(&volume_curve);
+
+			let meters_clone = meters.clone();
+			let max_volume = gio::SimpleAction::new_stateful("max_volume", glib::VariantTy::new("u").ok(), &(meters_clone.borrow().max_volume).to_variant());
+			max_volume.connect_activate(move |s, value| {
+				if let Some(max_volume) = value.and_then(|v| v.get::<u32>()) {
+					s.set_state(&meters_clone.borrow_mut().set_max_volume(max_volume).to_variant());
+				}
+			});
+			actions.add_action(&max_volume);
+
+			let meters_clone = meters.clone();
+			let volume_step = gio::SimpleAction::new_stateful("volume_step", glib::VariantTy::new("u").ok(), &(meters_clone.borrow().volume_step).to_variant());
+			volume_step.connect_activate(move |s, value| {
+				if let Some(step) = value.and_then(|v| v.get::<u32>()) {
+					s.set_state(&meters_clone.borrow_mut().set_volume_step(step).to_variant());
+				}
+			});
+			actions.add_action(&volume_step);
+
+			// The parameter is the percentage and the device name, separated by a colon.
+			// A percentage of 0 makes the device use the default maximum volume.
+			let meters_clone = meters.clone();
+			let device_max_volume = gio::SimpleAction::new("device_max_volume", glib::VariantTy::new("s").ok());
+			device_max_volume.connect_activate(move |_, value| {
+				if let Some(value) = value.and_then(|v| v.get::<String>()) {
+					if let Some(( percent, device )) = value.split_once(':') {
+						let percent = percent.parse::<u32>().ok().filter(|p| *p > 0);
+						meters_clone.borrow_mut().set_device_max_volume(device, percent);
+					}
+				}
+			});
+			actions.add_action(&device_max_volume);
+
 			let meters_clone = meters.clone();
 			let show_visualizers = gio::SimpleAction::new_stateful("show_visualizers", glib::VariantTy::new("bool").ok(), &(meters_clone.borrow().show_visualizers).to_variant());
 			show_visualizers.connect_activate(move |s, _| s.set_state(&meters_clone.borrow_mut().toggle_visualizers().to_variant()));
 			actions.add_action(&show_visualizers);
 		}
 
-		Self {
+		let mut myxer = Self {
 			window,
+			content,
+			stack,
+			switcher: stack_switcher,
 			pulse: pulse.clone(),
 			meters,
-			profiles
+			state: ConnectionState::Lost,
+			profiles,
+			modules,
+			virtual_device,
+			speaker_test,
+			remembered,
+			hidden_streams,
+			server_info,
+			connect_server,
+			event_log,
+			ducking
+		};
+
+		myxer.update_state();
+		myxer
+	}
+
+
+	/**
+	 * Swaps between the meters and the reconnecting placeholder
+	 * if the pulse connection state has changed.
+	 */
+
+	fn update_state(&mut self) {
+		let state = self.pulse.borrow().store().state;
+		if state == self.state { return; }
+		self.state = state;
+
+		let ready = self.state == ConnectionState::Ready;
+		self.content.set_visible_child_name(if ready { "meters" } else { "reconnecting" });
+		self.switcher.set_sensitive(ready);
+	}
+
+
+	/**
+	 * Requests peaks for the meters that are currently displayed, so that
+	 * streams on the hidden tab and inactive devices aren't monitored.
+	 * Nothing is monitored if peaks aren't being visualized.
+	 */
+
+	fn update_monitored(&mut self) {
+		let mut pulse = self.pulse.borrow_mut();
+		let mut monitored = HashSet::new();
+
+		if self.meters.borrow().show_visualizers && self.state == ConnectionState::Ready {
+			let store = pulse.store();
+			if self.stack.get_visible_child_name().map_or(false, |name| name == "input") {
+				monitored.insert((StreamType::Source, store.active_source));
+				monitored.extend(store.source_outputs.iter().filter(|( _, stream )| !is_own_stream(&stream.data.name, &stream.app))
+					.map(|( index, _ )| (StreamType::SourceOutput, *index)));
+			}
+			else {
+				monitored.insert((StreamType::Sink, store.active_sink));
+				monitored.extend(store.sink_inputs.iter().filter(|( _, stream )| !is_own_stream(&stream.data.name, &stream.app))
+					.map(|( index, _ )| (StreamType::SinkInput, *index)));
+			}
 		}
+
+		pulse.set_monitored(monitored);
 	}
 
 
 	/**
 	 * Updates the app's widgets based on information stored in the Pulse instance.
-	 * Kills the Card Profiles window if it has been requested.
+	 * Kills the Card Profiles, Modules, New Virtual Device, Speaker Test, Remembered Volumes, Hidden Streams, Server Info, Connect to Server, Event Log, and Ducking windows if it has been requested.
 	 */
 
 	pub fn update(&mut self) {
@@ -384,8 +977,49 @@ impl Myxer {
 		if let Some(profiles) = self.profiles.borrow_mut().as_mut() { kill = !profiles.update(); }
 		if kill { self.profiles.replace(None); }
 
-		if self.pulse.borrow_mut().update() {
+		let mut kill = false;
+		if let Some(modules) = self.modules.borrow_mut().as_mut() { kill = !modules.update(); }
+		if kill { self.modules.replace(None); }
+
+		let mut kill = false;
+		if let Some(virtual_device) = self.virtual_device.borrow_mut().as_mut() { kill = !virtual_device.update(); }
+		if kill { self.virtual_device.replace(None); }
+
+		let mut kill = false;
+		if let Some(speaker_test) = self.speaker_test.borrow_mut().as_mut() { kill = !speaker_test.update(); }
+		if kill { self.speaker_test.replace(None); }
+
+		let mut kill = false;
+		if let Some(remembered) = self.remembered.borrow_mut().as_mut() { kill = !remembered.update(); }
+		if kill { self.remembered.replace(None); }
+
+		let mut kill = false;
+		if let Some(hidden_streams) = self.hidden_streams.borrow_mut().as_mut() { kill = !hidden_streams.update(); }
+		if kill { self.hidden_streams.replace(None); }
+
+		let mut kill = false;
+		if let Some(server_info) = self.server_info.borrow_mut().as_mut() { kill = !server_info.update(); }
+		if kill { self.server_info.replace(None); }
+
+		let mut kill = false;
+		if let Some(connect_server) = self.connect_server.borrow_mut().as_mut() { kill = !connect_server.update(); }
+		if kill { self.connect_server.replace(None); }
+
+		let mut kill = false;
+		if let Some(event_log) = self.event_log.borrow_mut().as_mut() { kill = !event_log.update(); }
+		if kill { self.event_log.replace(None); }
+
+		let mut kill = false;
+		if let Some(ducking) = self.ducking.borrow_mut().as_mut() { kill = !ducking.update(); }
+		if kill { self.ducking.replace(None); }
+
+		let received = self.pulse.borrow_mut().update();
+		self.update_state();
+		self.update_monitored();
+
+		if received {
 			let mut pulse = self.pulse.borrow_mut();
+			let store = pulse.store_mut();
 
 			let mut meters = self.meters.borrow_mut();
 
@@ -400,16 +1034,19 @@ impl Myxer {
 
 			let show = meters.show_visualizers;
 			let separate = meters.separate_channels;
+			let stream_options = meters.scale_options(None);
 
 
-			if let Some(sink) = pulse.sinks.get(&pulse.active_sink) {
+			if let Some(sink) = store.sinks.get(&store.active_sink) {
 				meters.sink.set_data(&sink.data);
 
 				// refresh the peaks if they have changed OR if the split channels setting has changed
-				let peak = if show { Some(sink.peak) } else { None };
+				let peak = if show { Some(sink.peak.clone()) } else { None };
 				let refresh_peaks = (meters.sink.peak != peak) || (meters.sink.split != separate && show);
 
 				meters.sink.split_channels(separate);
+				let options = meters.scale_options(Some(&sink.data.name));
+				meters.sink.set_scale_options(options);
 
 				if refresh_peaks {
 					meters.sink.set_peak(peak);
@@ -419,63 +1056,73 @@ impl Myxer {
 			const DECREASE: u32 = 2000;
 			const REPETITIONS: u32 = 3;
 
-			for (index, input) in &mut pulse.sink_inputs {
+			for (index, input) in &mut store.sink_inputs {
 				let sink_inputs_box = meters.sink_inputs_box.clone();
 
 				let meter = meters.sink_inputs.entry(*index).or_insert_with(|| StreamMeter::new(self.pulse.clone()));
 				if meter.widget.get_parent().is_none() { sink_inputs_box.pack_start(&meter.widget, false, false, 0); }
 
 				// gradually decrease the peak value if it is not changing
-				if input.peak != 0 && Some(input.peak) == meter.peak {
+				if input.peak.iter().any(|p| *p != 0) && Some(&input.peak) == meter.peak.as_ref() {
 					if input.repetitions < REPETITIONS { input.repetitions += 1; }
 					else {
-						if input.peak < DECREASE { input.peak = 0; }
-						else { input.peak -= DECREASE; }
+						for peak in &mut input.peak { *peak = peak.saturating_sub(DECREASE); }
 					}
 				}
 				else { input.repetitions = 0; }
 
 				// refresh the peaks if they have changed OR if the split channels setting has changed
-				let peak = if show { Some(input.peak) } else { None };
+				let peak = if show { Some(input.peak.clone()) } else { None };
 				let refresh_peaks = (meter.peak != peak) || (meter.split != separate && show);
 
 				meter.set_data(&input.data);
 				meter.split_channels(separate);
+				meter.set_scale_options(stream_options);
 
 				if refresh_peaks {
 					meter.set_peak(peak);
 				}
 			}
 
-			let sink_inputs_box = meters.sink_inputs_box.clone();
+			// Meters of grouped streams are inside their group meter, rather than the streams box.
 			meters.sink_inputs.retain(|index, meter| {
-				let keep = pulse.sink_inputs.contains_key(index);
-				if !keep { sink_inputs_box.remove(&meter.widget); }
+				let keep = store.sink_inputs.contains_key(index);
+				if !keep {
+					if let Some(parent) = meter.widget.get_parent() { parent.downcast::<gtk::Container>().unwrap().remove(&meter.widget); }
+				}
 				keep
 			});
+			meters.update_groups(&self.pulse, StreamType::SinkInput, &store.sink_inputs);
 
-			if let Some(source) = pulse.sources.get(&pulse.active_source) {
+			if let Some(source) = store.sources.get(&store.active_source) {
 				meters.source.set_data(&source.data);
 				meters.source.split_channels(separate);
-				meters.source.set_peak(if show { Some(source.peak) } else { None });
+				let options = meters.scale_options(Some(&source.data.name));
+				meters.source.set_scale_options(options);
+				meters.source.set_loopback(store.loopbacks.contains_key(&store.active_source));
+				meters.source.set_peak(if show { Some(source.peak.clone()) } else { None });
 			}
 
-			for (index, output) in &pulse.source_outputs {
+			for (index, output) in &store.source_outputs {
 				let source_outputs_box = meters.source_outputs_box.clone();
 
 				let meter = meters.source_outputs.entry(*index).or_insert_with(|| StreamMeter::new(self.pulse.clone()));
 				if meter.widget.get_parent().is_none() { source_outputs_box.pack_start(&meter.widget, false, false, 0); }
 				meter.set_data(&output.data);
+				meter.set_device(store.sources.get(&output.monitor_index).map(|s| s.data.description.as_str()));
 				meter.split_channels(separate);
-				meter.set_peak(if show { Some(output.peak) } else { None });
+				meter.set_scale_options(stream_options);
+				meter.set_peak(if show { Some(output.peak.clone()) } else { None });
 			}
 
-			let source_outputs_box = meters.source_outputs_box.clone();
 			meters.source_outputs.retain(|index, meter| {
-				let keep = pulse.source_outputs.contains_key(index);
-				if !keep { source_outputs_box.remove(&meter.widget); }
+				let keep = store.source_outputs.contains_key(index);
+				if !keep {
+					if let Some(parent) = meter.widget.get_parent() { parent.downcast::<gtk::Container>().unwrap().remove(&meter.widget); }
+				}
 				keep
 			});
+			meters.update_groups(&self.pulse, StreamType::SourceOutput, &store.source_outputs);
 
 			meters.sink_inputs_box.show_all();
 			meters.source_outputs_box.show_all();
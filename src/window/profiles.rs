@@ -7,7 +7,7 @@ use std::collections::HashMap;
 use gtk::prelude::*;
 
 use crate::card::Card;
-use crate::pulse::Pulse;
+use crate::backend::Backend;
 use crate::shared::Shared;
 
 
@@ -39,7 +39,7 @@ impl Cards {
 
 pub struct Profiles {
 	cards: Shared<Cards>,
-	pulse: Shared<Pulse>,
+	pulse: Shared<dyn Backend>,
 
 	/** Indicates if the popup should remain open. */
 	live: Shared<bool>
@@ -51,7 +51,7 @@ impl Profiles {
 	 * Creates the Card Profiles window, and its contents.
 	 */
 
-	pub fn new(parent: &gtk::ApplicationWindow, pulse: &Shared<Pulse>) -> Self {
+	pub fn new(parent: &gtk::ApplicationWindow, pulse: &Shared<dyn Backend>) -> Self {
 		let dialog = gtk::Dialog::with_buttons(Some("Card Profiles"), Some(parent), gtk::DialogFlags::all(), &[]);
 		dialog.set_border_width(0);
 
@@ -96,7 +96,7 @@ impl Profiles {
 	pub fn update(&mut self) -> bool {
 		let pulse = self.pulse.borrow_mut();
 		let mut cards = self.cards.borrow_mut();
-		for (index, data) in &pulse.cards {
+		for (index, data) in &pulse.store().cards {
 			let cards_box = cards.cards_box.clone();
 			
 			let card = cards.cards.entry(*index).or_insert_with(|| Card::new(Some(self.pulse.clone())));
@@ -0,0 +1,132 @@
+/*!
+ * Contains the Remembered Volumes window.
+ */
+
+use gtk::prelude::*;
+
+use crate::backend::{ Backend, StreamType };
+use crate::meter::MAX_NATURAL_VOL;
+use crate::shared::Shared;
+
+
+/**
+ * The Remembered Volumes popup window.
+ * Lists the volumes remembered for applications, and allows them to be forgotten.
+ */
+
+pub struct Remembered {
+	rules_box: gtk::Box,
+	pulse: Shared<dyn Backend>,
+
+	/** The version of the rules that the list was last built from. */
+	version: Option<u32>,
+
+	/** Indicates if the popup should remain open. */
+	live: Shared<bool>
+}
+
+impl Remembered {
+
+	/**
+	 * Creates the Remembered Volumes window, and its contents.
+	 */
+
+	pub fn new(parent: &gtk::ApplicationWindow, pulse: &Shared<dyn Backend>) -> Self {
+		let dialog = gtk::Dialog::with_buttons(Some("Remembered Volumes"), Some(parent), gtk::DialogFlags::all(), &[]);
+		dialog.set_border_width(0);
+
+		let live = Shared::new(true);
+		dialog.connect_response(|s, _| s.emit_close());
+		let live_clone = live.clone();
+		dialog.connect_close(move |_| { live_clone.replace(false); });
+
+		let geom = gdk::Geometry {
+			min_width: 450, min_height: 400,
+			max_width: 450, max_height: 10000,
+			base_width: -1, base_height: -1,
+			width_inc: -1, height_inc: -1,
+			min_aspect: 0.0, max_aspect: 0.0,
+			win_gravity: gdk::Gravity::Center
+		};
+
+		dialog.set_geometry_hints::<gtk::Dialog>(None, Some(&geom), gdk::WindowHints::MIN_SIZE | gdk::WindowHints::MAX_SIZE);
+		let rules_box = gtk::Box::new(gtk::Orientation::Vertical, 8);
+
+		let scroller = gtk::ScrolledWindow::new::<gtk::Adjustment, gtk::Adjustment>(None, None);
+		scroller.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+		dialog.get_content_area().pack_start(&scroller, true, true, 0);
+		dialog.get_content_area().set_border_width(0);
+		scroller.add(&rules_box);
+
+		dialog.show_all();
+
+		Self {
+			rules_box,
+			pulse: pulse.clone(),
+			version: None,
+			live
+		}
+	}
+
+
+	/**
+	 * Rebuilds the list of remembered volumes if they have changed,
+	 * returns a boolean indicating if the window should continue to be open or not.
+	 */
+
+	pub fn update(&mut self) -> bool {
+		let pulse = self.pulse.borrow();
+		let rules = &pulse.store().rules;
+		if self.version == Some(rules.version) { return *self.live.borrow(); }
+		self.version = Some(rules.version);
+
+		for child in self.rules_box.get_children() { self.rules_box.remove(&child); }
+
+		if rules.rules.is_empty() {
+			let label = gtk::Label::new(Some("No volumes have been remembered yet."));
+			label.get_style_context().add_class("dim-label");
+			self.rules_box.pack_start(&label, false, false, 24);
+		}
+
+		for (i, rule) in rules.rules.iter().enumerate() {
+			let row = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+			row.set_border_width(6);
+
+			let icon = if rule.t == StreamType::SinkInput { "audio-speakers-symbolic" } else { "audio-input-microphone-symbolic" };
+			row.pack_start(&gtk::Image::from_icon_name(Some(icon), gtk::IconSize::LargeToolbar), false, false, 3);
+
+			let labels = gtk::Box::new(gtk::Orientation::Vertical, 0);
+			row.pack_start(&labels, true, true, 3);
+
+			let name = gtk::Label::new(Some(if rule.name.is_empty() { &rule.binary } else { &rule.name }));
+			name.set_halign(gtk::Align::Start);
+			name.set_ellipsize(pango::EllipsizeMode::End);
+			labels.pack_start(&name, false, false, 0);
+
+			let mut details = vec![ format!("{}%", (rule.volume as f64 / MAX_NATURAL_VOL as f64 * 100.0).round()) ];
+			if rule.muted { details.push("Muted".to_owned()); }
+			if !rule.binary.is_empty() { details.push(rule.binary.clone()); }
+			if !rule.role.is_empty() { details.push(rule.role.clone()); }
+
+			let detail = gtk::Label::new(Some(&details.join(" · ")));
+			detail.set_halign(gtk::Align::Start);
+			detail.set_ellipsize(pango::EllipsizeMode::End);
+			detail.get_style_context().add_class("dim-label");
+			labels.pack_start(&detail, false, false, 0);
+
+			let forget = gtk::Button::from_icon_name(Some("user-trash-symbolic"), gtk::IconSize::Button);
+			forget.set_tooltip_text(Some("Forget"));
+			forget.set_valign(gtk::Align::Center);
+			forget.get_style_context().add_class("flat");
+			let pulse = self.pulse.clone();
+			forget.connect_clicked(move |_| pulse.borrow_mut().store_mut().rules.remove(i));
+			row.pack_end(&forget, false, false, 3);
+
+			self.rules_box.pack_start(&row, false, false, 0);
+			self.rules_box.pack_start(&gtk::Separator::new(gtk::Orientation::Horizontal), false, false, 0);
+		}
+
+		self.rules_box.show_all();
+		*self.live.borrow()
+	}
+}
@@ -0,0 +1,107 @@
+/*!
+ * Contains the Server Info window.
+ */
+
+use gtk::prelude::*;
+
+use crate::backend::{ Backend, ServerData };
+use crate::shared::Shared;
+
+
+/**
+ * The Server Info popup window.
+ * Shows the same information as `pactl info`, and allows copying it to the clipboard.
+ */
+
+pub struct ServerInfo {
+	grid: gtk::Grid,
+	pulse: Shared<dyn Backend>,
+
+	/** The server information that the grid was last built from. */
+	server: Option<Option<ServerData>>,
+
+	/** Indicates if the popup should remain open. */
+	live: Shared<bool>
+}
+
+impl ServerInfo {
+
+	/**
+	 * Creates the Server Info window, and its contents.
+	 */
+
+	pub fn new(parent: &gtk::ApplicationWindow, pulse: &Shared<dyn Backend>) -> Self {
+		let dialog = gtk::Dialog::with_buttons(Some("Server Info"), Some(parent), gtk::DialogFlags::all(),
+			&[ ( "Copy", gtk::ResponseType::Apply ), ( "Close", gtk::ResponseType::Close ) ]);
+		dialog.set_resizable(false);
+
+		let live = Shared::new(true);
+		let live_clone = live.clone();
+		dialog.connect_close(move |_| { live_clone.replace(false); });
+
+		let pulse_clone = pulse.clone();
+		dialog.connect_response(move |dialog, response| {
+			if response != gtk::ResponseType::Apply { dialog.emit_close(); return; }
+
+			// The text is formatted like the output of `pactl info`, as that's what people are used to sharing.
+			if let Some(server) = &pulse_clone.borrow().store().server {
+				let text: Vec<String> = server.rows().iter().map(|( label, value )| format!("{}: {}", label, value)).collect();
+				gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD).set_text(&text.join("\n"));
+			}
+		});
+
+		let grid = gtk::Grid::new();
+		grid.set_border_width(12);
+		grid.set_row_spacing(6);
+		grid.set_column_spacing(12);
+		dialog.get_content_area().pack_start(&grid, true, true, 0);
+
+		dialog.show_all();
+
+		Self {
+			grid,
+			pulse: pulse.clone(),
+			server: None,
+			live
+		}
+	}
+
+
+	/**
+	 * Rebuilds the grid if the server information has changed,
+	 * returns a boolean indicating if the window should continue to be open or not.
+	 */
+
+	pub fn update(&mut self) -> bool {
+		let pulse = self.pulse.borrow();
+		let server = pulse.store().server.clone();
+		if self.server.as_ref() == Some(&server) { return *self.live.borrow(); }
+
+		for child in self.grid.get_children() { self.grid.remove(&child); }
+
+		match &server {
+			Some(server) => {
+				for (i, ( label, value )) in server.rows().iter().enumerate() {
+					let label = gtk::Label::new(Some(label));
+					label.set_halign(gtk::Align::End);
+					label.get_style_context().add_class("dim-label");
+					self.grid.attach(&label, 0, i as i32, 1, 1);
+
+					let value = gtk::Label::new(Some(value));
+					value.set_halign(gtk::Align::Start);
+					value.set_selectable(true);
+					self.grid.attach(&value, 1, i as i32, 1, 1);
+				}
+			},
+			None => {
+				let label = gtk::Label::new(Some("Not connected to the PulseAudio server."));
+				label.get_style_context().add_class("dim-label");
+				self.grid.attach(&label, 0, 0, 1, 1);
+			}
+		}
+
+		self.server = Some(server);
+		self.grid.show_all();
+		*self.live.borrow()
+	}
+}
@@ -0,0 +1,118 @@
+/*!
+ * Contains the Speaker Test window.
+ */
+
+use gtk::prelude::*;
+use libpulse::channelmap::Position;
+
+use crate::backend::Backend;
+use crate::shared::Shared;
+
+
+/**
+ * Gets the cell of the speaker layout that a channel position is shown in,
+ * as seen from above with the listener in the middle, facing the top.
+ * Returns None for positions that don't have a place in the layout, like auxiliary channels.
+ *
+ * * `position` - The channel position.
+ */
+
+fn layout_cell(position: Position) -> Option<( i32, i32 )> {
+	match position {
+		Position::FrontLeft => Some(( 0, 0 )),
+		Position::FrontLeftOfCenter => Some(( 1, 0 )),
+		Position::FrontCenter => Some(( 2, 0 )),
+		Position::FrontRightOfCenter => Some(( 3, 0 )),
+		Position::FrontRight => Some(( 4, 0 )),
+		Position::SideLeft => Some(( 0, 1 )),
+		Position::Mono | Position::Lfe => Some(( 2, 1 )),
+		Position::SideRight => Some(( 4, 1 )),
+		Position::RearLeft => Some(( 0, 2 )),
+		Position::RearCenter => Some(( 2, 2 )),
+		Position::RearRight => Some(( 4, 2 )),
+		_ => None
+	}
+}
+
+
+/**
+ * The Speaker Test popup window.
+ * Shows the channel layout of a sink, and plays a test tone on the channel that is clicked.
+ */
+
+pub struct SpeakerTest {
+	dialog: gtk::Dialog,
+	pulse: Shared<dyn Backend>,
+
+	/** The index of the sink being tested. */
+	sink: u32,
+
+	/** Indicates if the popup should remain open. */
+	live: Shared<bool>
+}
+
+impl SpeakerTest {
+
+	/**
+	 * Creates the Speaker Test window for the sink specified, and its contents.
+	 * The layout is built from the sink's channel map when the window is opened.
+	 */
+
+	pub fn new(parent: &gtk::ApplicationWindow, pulse: &Shared<dyn Backend>, sink: u32) -> Self {
+		let dialog = gtk::Dialog::with_buttons(Some("Speaker Test"), Some(parent), gtk::DialogFlags::all(), &[]);
+		dialog.set_resizable(false);
+
+		let live = Shared::new(true);
+		dialog.connect_response(|s, _| s.emit_close());
+		let live_clone = live.clone();
+		dialog.connect_close(move |_| { live_clone.replace(false); });
+
+		let grid = gtk::Grid::new();
+		grid.set_border_width(12);
+		grid.set_row_spacing(6);
+		grid.set_column_spacing(6);
+		grid.set_row_homogeneous(true);
+		grid.set_column_homogeneous(true);
+		dialog.get_content_area().pack_start(&grid, true, true, 0);
+
+		if let Some(device) = pulse.borrow().store().sinks.get(&sink) {
+			dialog.set_title(&format!("Speaker Test - {}", device.data.description));
+
+			// Channels without a place in the layout are listed in rows underneath it.
+			let mut unplaced = 0;
+			for (channel, position) in device.data.channel_map.get().iter().enumerate() {
+				let ( column, row ) = layout_cell(*position).unwrap_or_else(|| {
+					unplaced += 1;
+					( (unplaced - 1) % 5, 3 + (unplaced - 1) / 5 )
+				});
+
+				let label = Position::to_pretty_string(*position).unwrap_or_else(|| format!("Channel {}", channel + 1));
+				let button = gtk::Button::with_label(&label);
+				button.set_tooltip_text(Some(&format!("Play a test tone on {}", label)));
+				let pulse = pulse.clone();
+				button.connect_clicked(move |_| pulse.borrow_mut().play_test_tone(sink, channel));
+				grid.attach(&button, column, row, 1, 1);
+			}
+		}
+
+		dialog.show_all();
+
+		Self {
+			dialog,
+			pulse: pulse.clone(),
+			sink,
+			live
+		}
+	}
+
+
+	/**
+	 * Closes the window if the sink being tested has been removed.
+	 * Returns a boolean indicating if the window should continue to be open or not.
+	 */
+
+	pub fn update(&mut self) -> bool {
+		if *self.live.borrow() && !self.pulse.borrow().store().sinks.contains_key(&self.sink) { self.dialog.emit_close(); }
+		*self.live.borrow()
+	}
+}
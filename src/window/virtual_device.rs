@@ -0,0 +1,189 @@
+/*!
+ * Contains the New Virtual Device window, which creates sinks by loading modules.
+ */
+
+use gtk::prelude::*;
+
+use crate::backend::{ Backend, VIRTUAL_DEVICE_PREFIX };
+use crate::shared::Shared;
+
+
+/**
+ * Creates a unique sink name for a virtual device, based on its description.
+ *
+ * * `description` - The description of the device.
+ * * `existing`    - The names of the sinks that already exist.
+ */
+
+fn sink_name(description: &str, existing: &[String]) -> String {
+	let slug: String = description.to_lowercase().chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+	let base = format!("{}{}", VIRTUAL_DEVICE_PREFIX, if slug.is_empty() { "virtual" } else { &slug });
+
+	let mut name = base.clone();
+	let mut i = 2;
+	while existing.contains(&name) {
+		name = format!("{}_{}", base, i);
+		i += 1;
+	}
+
+	name
+}
+
+
+/**
+ * Creates the module arguments that name a sink and set its description.
+ * Quotes are removed from the description, as they can't be escaped inside the module arguments.
+ *
+ * * `name`        - The name of the sink.
+ * * `description` - The description of the sink.
+ */
+
+fn sink_arguments(name: &str, description: &str) -> String {
+	let description: String = description.chars().filter(|c| *c != '"' && *c != '\'' && *c != '\\').collect();
+	format!("sink_name={} sink_properties='device.description=\"{}\"'", name, description)
+}
+
+
+/**
+ * The New Virtual Device popup window.
+ * Creates null sinks, combined sinks, and remapped sinks.
+ */
+
+pub struct VirtualDevice {
+	/** Indicates if the popup should remain open. */
+	live: Shared<bool>
+}
+
+impl VirtualDevice {
+
+	/**
+	 * Creates the New Virtual Device window, and its contents.
+	 * The sinks that can be combined or remapped are the ones that exist when the window is opened.
+	 */
+
+	pub fn new(parent: &gtk::ApplicationWindow, pulse: &Shared<dyn Backend>) -> Self {
+		let dialog = gtk::Dialog::with_buttons(Some("New Virtual Device"), Some(parent), gtk::DialogFlags::all(),
+			&[ ( "Cancel", gtk::ResponseType::Cancel ), ( "Create", gtk::ResponseType::Accept ) ]);
+		dialog.set_default_response(gtk::ResponseType::Accept);
+		dialog.set_resizable(false);
+
+		let live = Shared::new(true);
+		let live_clone = live.clone();
+		dialog.connect_close(move |_| { live_clone.replace(false); });
+
+		let mut sinks: Vec<( String, String )> = pulse.borrow().store().sinks.values()
+			.map(|s| ( s.data.name.clone(), s.data.description.clone() )).collect();
+		sinks.sort_by(|a, b| a.1.cmp(&b.1));
+
+		let grid = gtk::Grid::new();
+		grid.set_border_width(12);
+		grid.set_row_spacing(6);
+		grid.set_column_spacing(12);
+		dialog.get_content_area().pack_start(&grid, true, true, 0);
+
+		let kind = gtk::ComboBoxText::new();
+		kind.append(Some("null"), "Null Output");
+		kind.append(Some("combine"), "Combined Output");
+		kind.append(Some("remap"), "Remapped Output");
+		kind.set_active_id(Some("null"));
+
+		let description = gtk::Entry::new();
+		description.set_placeholder_text(Some("Virtual Output"));
+		description.set_activates_default(true);
+
+		let combine_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+		let mut combined = Vec::new();
+		for ( name, label ) in &sinks {
+			let check = gtk::CheckButton::with_label(label);
+			combine_box.pack_start(&check, false, false, 0);
+			combined.push(( name.clone(), check ));
+		}
+
+		let master = gtk::ComboBoxText::new();
+		for ( name, label ) in &sinks { master.append(Some(name), label); }
+		master.set_active(Some(0));
+
+		let channels = gtk::ComboBoxText::new();
+		channels.append(Some("mono"), "Mono");
+		channels.append(Some("front-left,front-right"), "Stereo");
+		channels.append(Some("front-right,front-left"), "Stereo (Swapped)");
+		channels.set_active(Some(0));
+
+		let rows: [( &str, gtk::Widget, Option<&str> ); 5] = [
+			( "Type", kind.clone().upcast(), None ),
+			( "Name", description.clone().upcast(), None ),
+			( "Outputs", combine_box.upcast(), Some("combine") ),
+			( "Output", master.clone().upcast(), Some("remap") ),
+			( "Channels", channels.clone().upcast(), Some("remap") )
+		];
+
+		let mut conditional = Vec::new();
+		for (i, ( label, widget, kind )) in rows.iter().enumerate() {
+			let label = gtk::Label::new(Some(label));
+			label.set_halign(gtk::Align::End);
+			label.set_valign(gtk::Align::Start);
+			label.get_style_context().add_class("dim-label");
+			widget.set_hexpand(true);
+			grid.attach(&label, 0, i as i32, 1, 1);
+			grid.attach(widget, 1, i as i32, 1, 1);
+			if let Some(kind) = kind { conditional.push(( *kind, label.upcast::<gtk::Widget>(), widget.clone() )); }
+		}
+
+		let update_rows = move |kind: &gtk::ComboBoxText| {
+			let active = kind.get_active_id();
+			for ( kind, label, widget ) in &conditional {
+				let visible = active.as_deref() == Some(*kind);
+				label.set_visible(visible);
+				widget.set_visible(visible);
+			}
+		};
+
+		let pulse = pulse.clone();
+		let kind_clone = kind.clone();
+		dialog.connect_response(move |dialog, response| {
+			if response == gtk::ResponseType::Accept {
+				let text = description.get_text().trim().to_owned();
+				let text = if text.is_empty() { "Virtual Output".to_owned() } else { text };
+
+				let pulse = pulse.borrow();
+				let existing: Vec<String> = pulse.store().sinks.values().map(|s| s.data.name.clone()).collect();
+				let arguments = sink_arguments(&sink_name(&text, &existing), &text);
+
+				match kind_clone.get_active_id().as_deref() {
+					Some("combine") => {
+						let selected: Vec<&str> = combined.iter().filter(|( _, check )| check.get_active())
+							.map(|( name, _ )| name.as_str()).collect();
+						if selected.is_empty() { return; }
+						pulse.load_module("module-combine-sink", &format!("{} sinks={}", arguments, selected.join(",")));
+					},
+					Some("remap") => {
+						let master = match master.get_active_id() { Some(master) => master, None => return };
+						let map = channels.get_active_id().map(|c| c.to_string()).unwrap_or_else(|| "mono".to_owned());
+						let count = map.split(',').count();
+						pulse.load_module("module-remap-sink", &format!("{} master={} channels={} channel_map={}",
+							arguments, master, count, map));
+					},
+					_ => pulse.load_module("module-null-sink", &arguments)
+				}
+			}
+
+			dialog.emit_close();
+		});
+
+		dialog.show_all();
+		update_rows(&kind);
+		kind.connect_changed(update_rows);
+
+		Self { live }
+	}
+
+
+	/**
+	 * Returns a boolean indicating if the window should continue to be open or not.
+	 */
+
+	pub fn update(&mut self) -> bool {
+		*self.live.borrow()
+	}
+}